@@ -0,0 +1,78 @@
+//! Label aliases folded into a canonical name in reports.
+//!
+//! An alias file is TOML mapping each canonical label to its aliases:
+//!
+//! ```toml
+//! how-to = ["howto", "how_to"]
+//! ```
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Debug, Default)]
+pub struct TagAliases {
+    /// Canonical label for each alias
+    canonical: HashMap<String, String>,
+}
+
+impl TagAliases {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read alias file: {}", path))?;
+        Self::parse(&content).context(format!("Failed to parse alias file: {}", path))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let groups: HashMap<String, Vec<String>> = toml::from_str(content)?;
+        let mut canonical = HashMap::new();
+        for (name, aliases) in &groups {
+            for alias in aliases {
+                if alias == name {
+                    continue;
+                }
+                if groups.contains_key(alias) {
+                    bail!(
+                        "'{}' is both a canonical label and an alias of '{}'",
+                        alias,
+                        name
+                    );
+                }
+                if let Some(other) = canonical.insert(alias.clone(), name.clone()) {
+                    if &other != name {
+                        bail!("'{}' is an alias of both '{}' and '{}'", alias, other, name);
+                    }
+                }
+            }
+        }
+        Ok(Self { canonical })
+    }
+
+    /// Canonical name for `tag`; tags that are not aliases map to themselves
+    pub fn canonical<'a>(&'a self, tag: &'a str) -> &'a str {
+        self.canonical.get(tag).map_or(tag, String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_fold_into_canonical_and_reject_ambiguity() {
+        let aliases = TagAliases::parse(
+            r#"
+            how-to = ["howto", "how_to"]
+            faq = ["questions"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(aliases.canonical("howto"), "how-to");
+        assert_eq!(aliases.canonical("how-to"), "how-to");
+        assert_eq!(aliases.canonical("questions"), "faq");
+        assert_eq!(aliases.canonical("other"), "other");
+
+        assert!(TagAliases::parse("a = [\"x\"]\nb = [\"x\"]").is_err());
+        assert!(TagAliases::parse("a = [\"b\"]\nb = []").is_err());
+    }
+}
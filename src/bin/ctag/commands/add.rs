@@ -1,10 +1,13 @@
+use crate::commands::RunOptions;
 use crate::ui;
 use anyhow::Result;
 use clap::Args;
 use colored::Colorize;
 use ctag::api::ConfluenceClient;
-use ctag::models::ProcessResults;
+use ctag::models::{ProcessResults, TagResult};
+use ctag::rules::TagRules;
 use dialoguer::Confirm;
+use std::time::Instant;
 
 #[derive(Args)]
 #[command(after_help = "\
@@ -21,15 +24,28 @@ EXAMPLES:
   # Interactive mode with confirmation
   ctag add --interactive 'label = review' approved
 
+  # Add tags generated by another tool, one per line
+  some-generator | ctag add 'space = DOCS' --stdin-tags
+
+  # Leave a page untouched unless every tag can be added
+  ctag add 'space = DOCS' reviewed approved --atomic
+
+  # Also remove tags implied by a rules file (e.g. published removes draft)
+  ctag add 'label = draft' published --rules rules.toml
+
 ")]
 pub struct AddArgs {
     /// CQL expression to match pages
     pub cql_expression: String,
 
     /// Tags to add
-    #[arg(required = true)]
+    #[arg(required_unless_present = "stdin_tags", conflicts_with = "stdin_tags")]
     pub tags: Vec<String>,
 
+    /// Read the tags to add from stdin, one per line
+    #[arg(long, conflicts_with = "interactive")]
+    pub stdin_tags: bool,
+
     /// Confirm each action interactively
     #[arg(long)]
     pub interactive: bool,
@@ -37,59 +53,137 @@ pub struct AddArgs {
     /// Key to abort all operations in interactive mode
     #[arg(long, default_value = "q")]
     pub abort_key: String,
+
+    /// Ask once per space whether to change its pages before any is
+    /// changed; pages in declined spaces count as skipped. With
+    /// --interactive, pages in approved spaces are then confirmed one by one
+    #[arg(long)]
+    pub confirm_each_space: bool,
+
+    /// TOML rules file with tags to remove when a tag is added
+    #[arg(long, value_name = "FILE")]
+    pub rules: Option<String>,
+
+    /// All or nothing per page: if any tag fails, remove the tags this run
+    /// added so the page is left unchanged
+    #[arg(long)]
+    pub atomic: bool,
+
+    /// Skip pages you don't have permission to edit instead of failing on them
+    #[arg(long)]
+    pub skip_no_edit: bool,
+
+    /// After the run, re-read each changed page and print its labels, to
+    /// confirm the changes approved in an --interactive session landed
+    #[arg(long)]
+    pub review: bool,
+
+    /// After applying, wait this long (e.g. 15s) and report how many changed
+    /// pages already show their new labels in search
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub verify_after: Option<std::time::Duration>,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
 }
 
 pub fn run(
-    args: AddArgs,
+    mut args: AddArgs,
     client: &ConfluenceClient,
-    dry_run: bool,
-    show_progress: bool,
-    format: ctag::models::OutputFormat,
-) -> Result<()> {
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
     let verbose = format.is_verbose();
     if verbose {
         ui::print_header("ADD TAGS");
     }
+    let page_filter = args.query.page_filter()?;
+    if args.stdin_tags {
+        args.tags = crate::commands::read_stdin_tags()?;
+    }
+    let implied_removals = match &args.rules {
+        Some(path) => TagRules::from_file(path)?
+            .removals_for_add(&args.tags)
+            .iter()
+            .map(|tag| opts.normalize_tag(tag))
+            .collect(),
+        None => Vec::new(),
+    };
+
     // Get matching pages
-    let pages = crate::commands::get_matching_pages(
+    let started = Instant::now();
+    let pages = args.query.find_pages(
         client,
         &args.cql_expression,
-        100,
+        ctag::api::SEARCH_EXPAND,
         format,
-        show_progress,
+        opts.show_progress,
     )?;
+    let (pages, excluded) = crate::commands::exclude_pages(
+        client,
+        pages,
+        args.query.cql_exclude.as_deref(),
+        format,
+        opts.show_progress,
+    )?;
+    opts.timing.record("search", started);
+    let (pages, filtered) = page_filter.apply(pages);
+    let filtered = filtered + excluded;
+    let (pages, not_editable) =
+        crate::commands::retain_editable(client, pages, args.skip_no_edit, opts.read_concurrency);
 
     if pages.is_empty() {
-        ui::print_warning("No pages found matching the CQL expression.");
-        if dry_run {
-            ui::print_dry_run("No changes will be made.");
-        }
-        return Ok(());
+        return Ok(opts.no_pages_found());
     }
 
     if verbose {
         ui::print_info(&format!("Found {} matching pages.", pages.len()));
+        if filtered > 0 {
+            ui::print_info(&format!(
+                "Skipped {} pages filtered out by --title-match, --ancestor or --cql-exclude.",
+                filtered
+            ));
+        }
     }
 
-    if dry_run {
+    if opts.dry_run {
         ui::print_dry_run("No changes will be made.");
-        for page in &pages {
+        for (i, page) in pages.iter().enumerate() {
+            if opts.preview_exhausted(i) {
+                ui::print_preview_remainder(pages.len() - i);
+                break;
+            }
             let space = page.space_name();
-            let display_title = page.printable_clickable_title(client.base_url());
+            let display_title = page.printable_clickable_title(&client.web_base_url());
 
             ui::print_page_action("Would add tags to", &display_title, space);
             for tag in &args.tags {
                 ui::print_substep(&format!("{}: {}", "Add".green(), tag));
             }
+            for tag in &implied_removals {
+                ui::print_substep(&format!("{}: {} (if present)", "Remove".red(), tag));
+            }
         }
-        return Ok(());
+        return Ok(ProcessResults::new(pages.len()));
     }
 
+    let approval = if args.confirm_each_space {
+        crate::commands::confirm_each_space(pages)
+    } else {
+        crate::commands::SpaceApproval {
+            pages,
+            declined: 0,
+            aborted: false,
+        }
+    };
+    let pages = approval.pages;
+
     // Process the pages
+    let started = Instant::now();
     let mut results = ProcessResults::new(pages.len());
     if args.interactive {
         // Interactive mode: sequential processing
-        let progress = if show_progress {
+        let progress = if opts.show_progress {
             Some(ui::create_progress_bar(pages.len() as u64))
         } else {
             None
@@ -103,7 +197,7 @@ pub fn run(
                 }
             };
             let space = page.space_name();
-            let display_title = page.printable_clickable_title(client.base_url());
+            let display_title = page.printable_clickable_title(&client.web_base_url());
             if let Some(pb) = &progress {
                 pb.suspend(|| {
                     ui::print_page_action("Adding tags to", &display_title, space);
@@ -140,14 +234,35 @@ pub fn run(
                     break;
                 }
             }
-            let success = client.add_tags(page_id, &args.tags);
+            let (tag_results, tags_added) =
+                add_page_tags(client, page_id, &args.tags, args.atomic, opts);
             results.processed += 1;
-            if success {
-                results.success += 1;
+            let mut detail = ctag::models::ActionDetail {
+                page_id: page_id.to_string(),
+                title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+                space: space.to_string(),
+                url: display_title.clone(),
+                tags_added,
+                tags_removed: vec![],
+                tag_results,
+            };
+            if TagResult::all_succeeded(&detail.tag_results) {
                 results.tags_added += args.tags.len();
+                match remove_implied(client, page_id, &implied_removals, opts) {
+                    Ok(removed) => {
+                        results.success += 1;
+                        results.tags_removed += removed.len();
+                        detail.tags_removed = removed;
+                    }
+                    Err(e) => {
+                        ui::print_error(&format!("Rule removals failed: {:#}", e));
+                        results.failed += 1;
+                    }
+                }
             } else {
                 results.failed += 1;
             }
+            results.details.push(detail);
             if let Some(pb) = &progress {
                 pb.inc(1);
             }
@@ -157,32 +272,218 @@ pub fn run(
         }
     } else {
         // Non-interactive mode: parallel processing
-        results = crate::commands::process_pages_parallel(&pages, show_progress, |page| {
+        results = crate::commands::process_pages_parallel(&pages, opts, |page| {
             let page_id = match page.page_id() {
                 Some(id) => id,
                 None => return crate::commands::ActionResult::Skipped,
             };
-            if client.add_tags(page_id, &args.tags) {
-                let detail = ctag::models::ActionDetail {
-                    page_id: page_id.to_string(),
-                    title: page.title.as_deref().unwrap_or("Unknown").to_string(),
-                    space: page.space_name().to_string(),
-                    url: page.printable_clickable_title(client.base_url()),
-                    tags_added: args.tags.clone(),
-                    tags_removed: vec![],
-                };
-                crate::commands::ActionResult::Success {
-                    added: args.tags.len(),
-                    removed: 0,
+            let (tag_results, tags_added) =
+                add_page_tags(client, page_id, &args.tags, args.atomic, opts);
+            let mut detail = ctag::models::ActionDetail {
+                page_id: page_id.to_string(),
+                title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+                space: page.space_name().to_string(),
+                url: page.printable_clickable_title(&client.web_base_url()),
+                tags_added,
+                tags_removed: vec![],
+                tag_results,
+            };
+            if !TagResult::all_succeeded(&detail.tag_results) {
+                return crate::commands::ActionResult::Failed {
                     detail: Some(detail),
+                };
+            }
+            match remove_implied(client, page_id, &implied_removals, opts) {
+                Ok(removed) => detail.tags_removed = removed,
+                Err(e) => {
+                    log::error!("Rule removals failed on page {}: {:#}", page_id, e);
+                    return crate::commands::ActionResult::Failed {
+                        detail: Some(detail),
+                    };
                 }
-            } else {
-                crate::commands::ActionResult::Failed
+            }
+            crate::commands::ActionResult::Success {
+                added: detail.tags_added.len(),
+                removed: detail.tags_removed.len(),
+                detail: Some(detail),
             }
         });
     }
 
+    opts.timing.record("apply", started);
+
+    // Pages removed by client-side filters or in declined spaces count as
+    // skipped
+    results.total += filtered + not_editable + approval.declined;
+    results.skipped += filtered + not_editable + approval.declined;
+    results.aborted |= approval.aborted;
+    results.record_pages(pages.iter().filter_map(|p| p.page_id()));
+
+    if args.review {
+        crate::commands::review_changes(client, &results.details, opts);
+    }
+    if let Some(delay) = args.verify_after {
+        crate::commands::report_search_visibility(client, &results.details, delay);
+    }
+
     // Display results
-    ui::print_summary(&results, format);
-    Ok(())
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
+}
+
+/// Add `tags` to a page, returning the per-tag results and the tags left on
+/// the page. With `atomic`, a partial failure rolls back the tags this call
+/// added, so nothing is left added.
+fn add_page_tags(
+    client: &ConfluenceClient,
+    page_id: &str,
+    tags: &[String],
+    atomic: bool,
+    opts: &RunOptions,
+) -> (Vec<TagResult>, Vec<String>) {
+    if !atomic {
+        let results = client.add_tags(page_id, tags);
+        let added = TagResult::succeeded(&results);
+        return (results, added);
+    }
+    // Tags already on the page must survive a rollback
+    let before = match client.get_page_tags(page_id) {
+        Ok(tags) => tags,
+        Err(e) => {
+            log::error!("Could not read tags of page {}: {:#}", page_id, e);
+            let results = tags
+                .iter()
+                .map(|tag| TagResult {
+                    tag: tag.clone(),
+                    success: false,
+                })
+                .collect();
+            return (results, Vec::new());
+        }
+    };
+    let results = client.add_tags(page_id, tags);
+    if TagResult::all_succeeded(&results) {
+        let added = TagResult::succeeded(&results);
+        return (results, added);
+    }
+    let rollback = newly_added(&before, &results, |tag| opts.normalize_tag(tag));
+    let undone = client.remove_tags(page_id, &rollback);
+    if TagResult::all_succeeded(&undone) {
+        ui::defer_warning(format!(
+            "Page {}: not every tag could be added; rolled back {:?}",
+            page_id, rollback
+        ));
+        (results, Vec::new())
+    } else {
+        let removed = TagResult::succeeded(&undone);
+        let stuck: Vec<String> = rollback
+            .into_iter()
+            .filter(|tag| !removed.contains(tag))
+            .collect();
+        ui::print_error(&format!(
+            "Page {}: rollback failed; {:?} are still on the page",
+            page_id, stuck
+        ));
+        (results, stuck)
+    }
+}
+
+/// Tags that were added successfully and were not on the page before,
+/// compared after `normalize` so `Draft` matches an existing `draft` when
+/// labels are stored lowercase
+fn newly_added(
+    before: &[String],
+    results: &[TagResult],
+    normalize: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let before: Vec<String> = before.iter().map(|tag| normalize(tag)).collect();
+    TagResult::succeeded(results)
+        .into_iter()
+        .filter(|tag| !before.contains(&normalize(tag)))
+        .collect()
+}
+
+/// Remove the rule-implied tags that are actually present on the page,
+/// returning the ones removed.
+fn remove_implied(
+    client: &ConfluenceClient,
+    page_id: &str,
+    implied: &[String],
+    opts: &RunOptions,
+) -> Result<Vec<String>> {
+    if implied.is_empty() {
+        return Ok(Vec::new());
+    }
+    let current = client.get_page_tags(page_id)?;
+    let present = implied_present(&current, implied, |tag| opts.normalize_tag(tag));
+    if !TagResult::all_succeeded(&client.remove_tags(page_id, &present)) {
+        anyhow::bail!("could not remove {:?} from page {}", present, page_id);
+    }
+    Ok(present)
+}
+
+/// The page's labels, as stored, that match one of the implied tags once
+/// both are normalized
+fn implied_present(
+    current: &[String],
+    implied: &[String],
+    normalize: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let implied: Vec<String> = implied.iter().map(|tag| normalize(tag)).collect();
+    current
+        .iter()
+        .filter(|tag| implied.contains(&normalize(tag)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newly_added_skips_failed_and_preexisting_tags() {
+        let before = vec!["kept".to_string()];
+        let results = vec![
+            TagResult {
+                tag: "kept".to_string(),
+                success: true,
+            },
+            TagResult {
+                tag: "new".to_string(),
+                success: true,
+            },
+            TagResult {
+                tag: "broken".to_string(),
+                success: false,
+            },
+        ];
+        assert_eq!(newly_added(&before, &results, str::to_string), vec!["new"]);
+    }
+
+    #[test]
+    fn implied_present_matches_labels_across_case() {
+        let current = vec!["Draft".to_string(), "other".to_string()];
+        let implied = vec!["draft".to_string(), "missing".to_string()];
+        assert_eq!(
+            implied_present(&current, &implied, str::to_lowercase),
+            vec!["Draft"]
+        );
+        assert!(implied_present(&current, &implied, str::to_string).is_empty());
+    }
+
+    #[test]
+    fn newly_added_matches_preexisting_tags_across_case() {
+        let before = vec!["draft".to_string()];
+        let results = vec![TagResult {
+            tag: "Draft".to_string(),
+            success: true,
+        }];
+        // Rolling back `Draft` would delete the page's own `draft` label
+        assert!(newly_added(&before, &results, str::to_lowercase).is_empty());
+        assert_eq!(
+            newly_added(&before, &results, str::to_string),
+            vec!["Draft"]
+        );
+    }
 }
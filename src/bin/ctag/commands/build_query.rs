@@ -0,0 +1,92 @@
+use crate::commands::get::GetArgs;
+use crate::ui;
+use anyhow::{Context, Result};
+use clap::Args;
+use ctag::cql::CqlBuilder;
+use dialoguer::{Confirm, Input};
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # Answer a few questions and print the resulting CQL
+  ctag build-query
+
+  # Reuse the generated CQL with another command
+  ctag add \"$(ctag build-query --print-only)\" reviewed
+")]
+pub struct BuildQueryArgs {
+    /// Only print the generated CQL, without offering to run it
+    #[arg(long)]
+    pub print_only: bool,
+}
+
+/// Prompt for the parts of a query and print the assembled CQL. Returns the
+/// arguments for a `get` run when the user chooses to run the query.
+pub fn run(args: BuildQueryArgs) -> Result<Option<GetArgs>> {
+    if !args.print_only {
+        ui::print_header("BUILD QUERY");
+        ui::print_info("Leave a field empty to skip it.");
+    }
+
+    let builder = CqlBuilder {
+        space: prompt("Space key (e.g. DOCS)")?,
+        content_type: prompt("Content type (page, blogpost)")?,
+        label: prompt("Label")?,
+        text: prompt("Text contains")?,
+        modified_since: prompt("Modified since (-7d, 2024-01-31)")?,
+    };
+    let cql = builder
+        .build()
+        .context("At least one field is required to build a query")?;
+
+    if args.print_only {
+        println!("{}", cql);
+        return Ok(None);
+    }
+
+    ui::print_success(&format!("CQL: {}", cql));
+
+    let run_get = Confirm::new()
+        .with_prompt("Run `ctag get` with this query now?")
+        .default(false)
+        .interact()?;
+    if !run_get {
+        return Ok(None);
+    }
+
+    Ok(Some(GetArgs {
+        cql_expression: cql,
+        show_pages: true,
+        tags_only: false,
+        by_tag: false,
+        preserve_tag_order: false,
+        interactive: false,
+        abort_key: "q".to_string(),
+        output_file: None,
+        tree: false,
+        flat: false,
+        max_inflight_pages: None,
+        output_dir: None,
+        ids_only: false,
+        collapse_common_tags: false,
+        baseline: None,
+        alias_file: None,
+        case_report: false,
+        raw_labels: false,
+        with_history: false,
+        page_ids: Vec::new(),
+        untagged: false,
+        tagged: false,
+        detailed: false,
+        query: crate::commands::QueryArgs::default(),
+    }))
+}
+
+fn prompt(label: &str) -> Result<Option<String>> {
+    let value: String = Input::new()
+        .with_prompt(label)
+        .allow_empty(true)
+        .interact_text()?;
+    let value = value.trim();
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
@@ -0,0 +1,237 @@
+use crate::commands::RunOptions;
+use crate::ui;
+use anyhow::Result;
+use clap::Args;
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Attribute, Cell, Color, Table};
+use ctag::api::ConfluenceClient;
+use ctag::models::{OutputFormat, ProcessResults};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::AtomicUsize;
+use std::time::Instant;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # Which tags are shared between spaces and which are unique to one
+  ctag compare-spaces 'type = page'
+
+  # Compare two specific spaces as JSON
+  ctag compare-spaces 'space in (DOCS, ENG)' --format json
+")]
+pub struct CompareSpacesArgs {
+    /// CQL expression to match pages
+    pub cql_expression: String,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
+}
+
+/// Tags grouped by space, with the tags used in more than one space
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct SpaceComparison {
+    spaces: BTreeMap<String, BTreeSet<String>>,
+    shared: BTreeSet<String>,
+}
+
+impl SpaceComparison {
+    fn from_pages<'a>(pages: impl IntoIterator<Item = (&'a str, &'a [String])>) -> Self {
+        let mut spaces: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for (space, tags) in pages {
+            spaces
+                .entry(space.to_string())
+                .or_default()
+                .extend(tags.iter().cloned());
+        }
+        let shared = Self::tag_spaces(&spaces)
+            .into_iter()
+            .filter(|(_, in_spaces)| in_spaces.len() > 1)
+            .map(|(tag, _)| tag)
+            .collect();
+        Self { spaces, shared }
+    }
+
+    /// For each tag, the spaces it appears in
+    fn tag_spaces(spaces: &BTreeMap<String, BTreeSet<String>>) -> BTreeMap<String, Vec<&str>> {
+        let mut by_tag: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for (space, tags) in spaces {
+            for tag in tags {
+                by_tag.entry(tag.clone()).or_default().push(space);
+            }
+        }
+        by_tag
+    }
+}
+
+pub fn run(
+    args: CompareSpacesArgs,
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
+    let verbose = format.is_verbose();
+    if verbose {
+        ui::print_header("COMPARE SPACES");
+    }
+    let page_filter = args.query.page_filter()?;
+
+    let started = Instant::now();
+    let pages = args.query.find_pages(
+        client,
+        &args.cql_expression,
+        ctag::api::SEARCH_EXPAND,
+        format,
+        opts.show_progress,
+    )?;
+    let (pages, _) = crate::commands::exclude_pages(
+        client,
+        pages,
+        args.query.cql_exclude.as_deref(),
+        format,
+        opts.show_progress,
+    )?;
+    opts.timing.record("search", started);
+    let (pages, _) = page_filter.apply(pages);
+
+    if pages.is_empty() {
+        if format.is_structured() {
+            print_comparison(&SpaceComparison::default(), format, &opts.csv)?;
+        } else {
+            opts.print_empty_message();
+        }
+        return Ok(ProcessResults::new(0));
+    }
+    if verbose {
+        ui::print_info(&format!("Found {} matching pages.", pages.len()));
+    }
+
+    let progress = if opts.show_progress && !format.is_structured() {
+        Some(ui::create_progress_bar(pages.len() as u64))
+    } else {
+        None
+    };
+    let started = Instant::now();
+    let page_data = crate::commands::in_pool(opts.read_concurrency, || {
+        crate::commands::get::collect_page_data(
+            &pages,
+            client,
+            progress.as_ref(),
+            &AtomicUsize::new(0),
+            false,
+            false,
+        )
+    });
+    opts.timing.record("labels", started);
+    if let Some(p) = &progress {
+        p.finish_and_clear();
+    }
+
+    let comparison = SpaceComparison::from_pages(
+        page_data
+            .iter()
+            .map(|p| (p.space.as_str(), p.tags.as_slice())),
+    );
+    print_comparison(&comparison, format, &opts.csv)?;
+
+    let mut results = ProcessResults::new(pages.len());
+    results.processed = page_data.len();
+    results.success = page_data.len();
+    results.skipped = pages.len() - page_data.len();
+    Ok(results)
+}
+
+fn print_comparison(
+    comparison: &SpaceComparison,
+    format: OutputFormat,
+    csv_options: &ui::CsvOptions,
+) -> Result<()> {
+    let by_tag = SpaceComparison::tag_spaces(&comparison.spaces);
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(comparison)?),
+        OutputFormat::Csv => {
+            #[derive(Serialize)]
+            struct TagRow<'a> {
+                tag: &'a str,
+                spaces: String,
+                shared: bool,
+            }
+            let mut wtr = csv_options.writer(std::io::stdout())?;
+            for (tag, spaces) in &by_tag {
+                wtr.serialize(TagRow {
+                    tag,
+                    spaces: spaces.join(";"),
+                    shared: spaces.len() > 1,
+                })?;
+            }
+            wtr.flush()?;
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            if by_tag.is_empty() {
+                println!("No tags found.");
+                return Ok(());
+            }
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec![
+                    Cell::new("Tag")
+                        .add_attribute(Attribute::Bold)
+                        .fg(Color::Cyan),
+                    Cell::new("Spaces")
+                        .add_attribute(Attribute::Bold)
+                        .fg(Color::Cyan),
+                ]);
+            for (tag, spaces) in &by_tag {
+                let tag_cell = if spaces.len() > 1 {
+                    Cell::new(tag).fg(Color::Green)
+                } else {
+                    Cell::new(tag)
+                };
+                table.add_row(vec![tag_cell, Cell::new(spaces.join(", "))]);
+            }
+            println!("{}", table);
+            ui::print_info(&format!(
+                "{} spaces, {} tags, {} shared across spaces",
+                comparison.spaces.len(),
+                by_tag.len(),
+                comparison.shared.len()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn comparison_groups_by_space_and_finds_shared() {
+        let docs_a = tags(&["api", "draft"]);
+        let docs_b = tags(&["howto"]);
+        let eng = tags(&["api", "rfc"]);
+        let comparison = SpaceComparison::from_pages(vec![
+            ("Docs", docs_a.as_slice()),
+            ("Eng", eng.as_slice()),
+            ("Docs", docs_b.as_slice()),
+        ]);
+
+        assert_eq!(comparison.spaces.len(), 2);
+        assert_eq!(
+            comparison.spaces["Docs"],
+            BTreeSet::from(["api".to_string(), "draft".to_string(), "howto".to_string()])
+        );
+        assert_eq!(comparison.shared, BTreeSet::from(["api".to_string()]));
+
+        let json = serde_json::to_value(&comparison).unwrap();
+        assert_eq!(json["spaces"]["Eng"], serde_json::json!(["api", "rfc"]));
+        assert_eq!(json["shared"], serde_json::json!(["api"]));
+    }
+}
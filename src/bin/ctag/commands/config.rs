@@ -0,0 +1,315 @@
+use crate::ui;
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use ctag::api::{self, DeploymentKind, HttpOptions};
+use ctag::config::Config;
+use ctag::models::OutputFormat;
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # Show the settings a command would run with
+  ctag config show
+
+  # Check how flags change them
+  ctag --read-retries 2 --format json config show
+")]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration after config file, environment and flags
+    Show,
+}
+
+/// Global flags and loaded config that make up the effective configuration
+pub struct Settings<'a> {
+    pub config_path: Option<&'a Path>,
+    pub config: &'a Config,
+    pub format: OutputFormat,
+    pub csv: ui::CsvOptions,
+    pub http: &'a HttpOptions,
+    pub read_retries: u32,
+    pub write_retries: u32,
+    pub read_concurrency: usize,
+    pub write_concurrency: usize,
+    pub search_params: &'a [(String, String)],
+    pub raw_search_path: Option<&'a str>,
+    pub assume_labels_lowercase: Option<bool>,
+    pub journal: Option<&'a Path>,
+    pub deployment: Option<DeploymentKind>,
+}
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Serialize)]
+struct EffectiveConfig {
+    config_file: Option<String>,
+    config_file_exists: bool,
+    url: Option<String>,
+    deployment: String,
+    auth: Auth,
+    format: String,
+    /// Worker threads fetching labels
+    read_concurrency: usize,
+    /// Worker threads applying changes
+    write_concurrency: usize,
+    read_retries: u32,
+    write_retries: u32,
+    context_path: String,
+    assume_labels_lowercase: bool,
+    search_params: Vec<String>,
+    raw_search_path: Option<String>,
+    ca_cert: Option<String>,
+    danger_insecure: bool,
+    timeout_secs: u64,
+    proxy: Option<String>,
+    journal: Option<String>,
+    named_queries: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+enum Auth {
+    Basic {
+        username: Option<String>,
+        /// Always redacted; `None` when no token is set and ctag would prompt
+        token: Option<&'static str>,
+    },
+    Bearer {
+        /// Always redacted
+        token: &'static str,
+    },
+    Oauth {
+        client_id: String,
+        token_url: String,
+        access_token: &'static str,
+    },
+}
+
+impl EffectiveConfig {
+    /// Resolve the settings, reading environment variables through `env`
+    fn resolve(settings: &Settings, env: impl Fn(&str) -> Option<String>) -> Self {
+        let env = |name: &str| env(name).filter(|v| !v.trim().is_empty());
+        let url = env("ATLASSIAN_URL");
+        let auth = match &settings.config.oauth {
+            Some(oauth) => Auth::Oauth {
+                client_id: oauth.client_id.clone(),
+                token_url: oauth.token_url.clone(),
+                access_token: REDACTED,
+            },
+            None if env("ATLASSIAN_BEARER_TOKEN").is_some() => Auth::Bearer { token: REDACTED },
+            None => Auth::Basic {
+                username: env("ATLASSIAN_USERNAME"),
+                token: env("ATLASSIAN_TOKEN").map(|_| REDACTED),
+            },
+        };
+        let is_cloud = match settings.deployment {
+            Some(deployment) => deployment == DeploymentKind::Cloud,
+            None => url.as_deref().is_some_and(api::is_cloud_url),
+        };
+        let default_context_path = settings.deployment.map_or(
+            api::DEFAULT_CONTEXT_PATH,
+            DeploymentKind::default_context_path,
+        );
+        Self {
+            config_file: settings.config_path.map(|p| p.display().to_string()),
+            config_file_exists: settings.config_path.is_some_and(Path::exists),
+            url,
+            deployment: if is_cloud { "cloud" } else { "server" }.to_string(),
+            auth,
+            format: format!("{:?}", settings.format).to_lowercase(),
+            read_concurrency: settings.read_concurrency,
+            write_concurrency: settings.write_concurrency,
+            read_retries: settings.read_retries,
+            write_retries: settings.write_retries,
+            context_path: env("ATLASSIAN_CONTEXT_PATH")
+                .map(|p| api::normalize_context_path(&p))
+                .unwrap_or_else(|| default_context_path.to_string()),
+            assume_labels_lowercase: settings.assume_labels_lowercase.unwrap_or(is_cloud),
+            search_params: settings
+                .search_params
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect(),
+            raw_search_path: settings.raw_search_path.map(str::to_string),
+            ca_cert: settings
+                .http
+                .ca_cert
+                .as_ref()
+                .map(|p| p.display().to_string()),
+            danger_insecure: settings.http.danger_accept_invalid_certs,
+            timeout_secs: settings
+                .http
+                .timeout
+                .unwrap_or(api::DEFAULT_REQUEST_TIMEOUT)
+                .as_secs(),
+            proxy: settings.http.proxy.describe(),
+            journal: settings.journal.map(|p| p.display().to_string()),
+            named_queries: settings.config.queries.len(),
+        }
+    }
+
+    /// `(setting, value)` pairs for text output
+    fn rows(&self) -> Vec<(&'static str, String)> {
+        let unset = || "(not set)".to_string();
+        let mut rows = vec![
+            (
+                "config file",
+                match &self.config_file {
+                    Some(path) if self.config_file_exists => path.clone(),
+                    Some(path) => format!("{} (not found)", path),
+                    None => unset(),
+                },
+            ),
+            ("url", self.url.clone().unwrap_or_else(unset)),
+            ("deployment", self.deployment.clone()),
+        ];
+        match &self.auth {
+            Auth::Basic { username, token } => {
+                rows.push(("auth", "basic".to_string()));
+                rows.push(("username", username.clone().unwrap_or_else(unset)));
+                rows.push((
+                    "token",
+                    token.map_or("(not set, will prompt)".to_string(), str::to_string),
+                ));
+            }
+            Auth::Bearer { token } => {
+                rows.push(("auth", "bearer".to_string()));
+                rows.push(("token", token.to_string()));
+            }
+            Auth::Oauth {
+                client_id,
+                token_url,
+                access_token,
+            } => {
+                rows.push(("auth", "oauth".to_string()));
+                rows.push(("client id", client_id.clone()));
+                rows.push(("token url", token_url.clone()));
+                rows.push(("access token", access_token.to_string()));
+            }
+        }
+        rows.extend([
+            ("format", self.format.clone()),
+            ("read concurrency", self.read_concurrency.to_string()),
+            ("write concurrency", self.write_concurrency.to_string()),
+            ("read retries", self.read_retries.to_string()),
+            ("write retries", self.write_retries.to_string()),
+            ("context path", self.context_path.clone()),
+            (
+                "assume labels lowercase",
+                self.assume_labels_lowercase.to_string(),
+            ),
+            ("search params", self.search_params.join(", ")),
+            (
+                "raw search path",
+                self.raw_search_path.clone().unwrap_or_else(unset),
+            ),
+            ("ca cert", self.ca_cert.clone().unwrap_or_else(unset)),
+            ("danger insecure", self.danger_insecure.to_string()),
+            ("timeout", format!("{}s", self.timeout_secs)),
+            ("proxy", self.proxy.clone().unwrap_or_else(unset)),
+            ("journal", self.journal.clone().unwrap_or_else(unset)),
+            ("named queries", self.named_queries.to_string()),
+        ]);
+        rows
+    }
+}
+
+pub fn run(args: ConfigArgs, settings: &Settings) -> Result<()> {
+    match args.command {
+        ConfigCommand::Show => {
+            let effective = EffectiveConfig::resolve(settings, |name| std::env::var(name).ok());
+            match settings.format {
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&effective)?),
+                OutputFormat::Csv => {
+                    let mut wtr = settings.csv.writer(std::io::stdout())?;
+                    wtr.write_record(["setting", "value"])?;
+                    for (setting, value) in effective.rows() {
+                        wtr.write_record([setting, value.as_str()])?;
+                    }
+                    wtr.flush()?;
+                }
+                OutputFormat::Simple | OutputFormat::Verbose => {
+                    let rows = effective.rows();
+                    let width = rows.iter().map(|(s, _)| s.len()).max().unwrap_or(0);
+                    for (setting, value) in rows {
+                        println!("{:width$}  {}", setting, value, width = width);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings<'a>(config: &'a Config, http: &'a HttpOptions) -> Settings<'a> {
+        Settings {
+            config_path: None,
+            config,
+            format: OutputFormat::Json,
+            csv: ui::CsvOptions {
+                bom: false,
+                delimiter: b',',
+            },
+            http,
+            read_retries: 2,
+            write_retries: 0,
+            read_concurrency: 8,
+            write_concurrency: 2,
+            search_params: &[],
+            raw_search_path: None,
+            assume_labels_lowercase: None,
+            journal: None,
+            deployment: None,
+        }
+    }
+
+    #[test]
+    fn resolve_redacts_token_and_applies_env() {
+        let config = Config::default();
+        let http = HttpOptions::default();
+        let env = |name: &str| match name {
+            "ATLASSIAN_URL" => Some("https://example.atlassian.net".to_string()),
+            "ATLASSIAN_USERNAME" => Some("me@example.com".to_string()),
+            "ATLASSIAN_TOKEN" => Some("secret-token".to_string()),
+            "ATLASSIAN_CONTEXT_PATH" => Some("confluence/".to_string()),
+            _ => None,
+        };
+        let effective = EffectiveConfig::resolve(&settings(&config, &http), env);
+        let json = serde_json::to_string(&effective).unwrap();
+        assert!(!json.contains("secret-token"));
+        assert!(json.contains(r#""method":"basic""#));
+        assert_eq!(effective.context_path, "/confluence");
+        assert!(effective.assume_labels_lowercase);
+        assert_eq!(effective.write_retries, 0);
+        assert_eq!(effective.write_concurrency, 2);
+    }
+
+    #[test]
+    fn server_deployment_defaults_to_root_context_path() {
+        let config = Config::default();
+        let http = HttpOptions::default();
+        let settings = Settings {
+            deployment: Some(DeploymentKind::Server),
+            ..settings(&config, &http)
+        };
+        let env = |name: &str| match name {
+            "ATLASSIAN_URL" => Some("https://example.atlassian.net".to_string()),
+            _ => None,
+        };
+        let effective = EffectiveConfig::resolve(&settings, env);
+        assert_eq!(effective.deployment, "server");
+        assert_eq!(effective.context_path, "");
+        assert!(!effective.assume_labels_lowercase);
+    }
+}
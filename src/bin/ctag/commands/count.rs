@@ -0,0 +1,47 @@
+use crate::commands::RunOptions;
+use crate::ui;
+use anyhow::Result;
+use clap::Args;
+use ctag::api::ConfluenceClient;
+use ctag::models::{OutputFormat, ProcessResults};
+use std::time::Instant;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # How many pages are in a space
+  ctag count 'space = DOCS'
+
+  # Machine-readable
+  ctag --format json count 'label = draft'
+")]
+pub struct CountArgs {
+    /// CQL expression to match pages
+    pub cql_expression: String,
+}
+
+pub fn run(
+    args: CountArgs,
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let started = Instant::now();
+    let count = client.count_cql_results(&args.cql_expression)?;
+    opts.timing.record("search", started);
+
+    match opts.format {
+        OutputFormat::Json => println!("{}", serde_json::json!({ "count": count })),
+        OutputFormat::Csv => {
+            let mut wtr = opts.csv.writer(std::io::stdout())?;
+            wtr.write_record(["count"])?;
+            wtr.write_record([count.to_string()])?;
+            wtr.flush()?;
+        }
+        OutputFormat::Simple => println!("{}", count),
+        OutputFormat::Verbose => {
+            ui::print_info(&format!("{} pages match.", count));
+            println!("{}", count);
+        }
+    }
+    Ok(ProcessResults::new(count))
+}
@@ -0,0 +1,195 @@
+use crate::ui;
+use anyhow::Result;
+use clap::Args;
+use ctag::api::{ConfluenceClient, DeploymentKind, HttpOptions};
+use ctag::config::Config;
+use ctag::models::OutputFormat;
+use serde::Serialize;
+use std::env;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # Check configuration and connectivity before a first run
+  ctag doctor
+
+  # Machine-readable results
+  ctag doctor --format json
+")]
+pub struct DoctorArgs {}
+
+#[derive(Serialize)]
+struct CheckResult {
+    check: String,
+    ok: bool,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(check: &str, detail: String) -> Self {
+        Self {
+            check: check.to_string(),
+            ok: true,
+            detail,
+            hint: None,
+        }
+    }
+
+    fn fail(check: &str, detail: String, hint: &str) -> Self {
+        Self {
+            check: check.to_string(),
+            ok: false,
+            detail,
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+const REQUIRED_VARS: [&str; 3] = ["ATLASSIAN_URL", "ATLASSIAN_USERNAME", "ATLASSIAN_TOKEN"];
+const BEARER_TOKEN_VAR: &str = "ATLASSIAN_BEARER_TOKEN";
+
+pub fn run(
+    _args: DoctorArgs,
+    format: OutputFormat,
+    config: &Config,
+    http: &HttpOptions,
+    deployment: Option<DeploymentKind>,
+) -> Result<()> {
+    let mut checks = Vec::new();
+
+    // Environment variables; OAuth credentials come from the config file
+    // instead, and a bearer token replaces username and API token
+    let bearer_token = env::var(BEARER_TOKEN_VAR)
+        .ok()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    let required: &[&str] = if config.oauth.is_some() {
+        &REQUIRED_VARS[..1]
+    } else if bearer_token.is_some() {
+        &["ATLASSIAN_URL", BEARER_TOKEN_VAR]
+    } else {
+        &REQUIRED_VARS
+    };
+    let mut missing = Vec::new();
+    for &var in required {
+        match env::var(var) {
+            Ok(v) if !v.trim().is_empty() => {}
+            _ => missing.push(var),
+        }
+    }
+    if missing.is_empty() {
+        checks.push(CheckResult::pass(
+            "environment",
+            format!("{} set", required.join(", ")),
+        ));
+    } else {
+        checks.push(CheckResult::fail(
+            "environment",
+            format!("Missing: {}", missing.join(", ")),
+            "Set the variables in your shell or in a .env file (see .env.example)",
+        ));
+    }
+
+    // Connectivity and auth need all three variables
+    if missing.is_empty() {
+        let url = env::var("ATLASSIAN_URL")?;
+        let client = match (&config.oauth, bearer_token) {
+            (Some(oauth), _) => ConfluenceClient::with_auth(url, oauth.clone().into()),
+            (None, Some(token)) => ConfluenceClient::with_bearer_token(url, token),
+            (None, None) => ConfluenceClient::new(
+                url,
+                env::var("ATLASSIAN_USERNAME")?,
+                env::var("ATLASSIAN_TOKEN")?.trim().to_string(),
+            ),
+        }
+        .with_http_options(http)?;
+        let client = match deployment {
+            Some(deployment) => client.with_deployment(deployment),
+            None => client,
+        };
+        let client = match env::var("ATLASSIAN_CONTEXT_PATH") {
+            Ok(path) => client.with_context_path(&path),
+            Err(_) => client,
+        };
+
+        let reachable = match client.check_reachable() {
+            Ok(status) => {
+                checks.push(CheckResult::pass(
+                    "reachability",
+                    format!("{} responded with {}", client.base_url(), status),
+                ));
+                true
+            }
+            Err(e) => {
+                checks.push(CheckResult::fail(
+                    "reachability",
+                    format!("{}: {:#}", client.base_url(), e),
+                    "Check ATLASSIAN_URL (e.g. https://your-domain.atlassian.net) and your network/VPN",
+                ));
+                false
+            }
+        };
+
+        if reachable {
+            match client.get_current_user() {
+                Ok(user) if user.get("type").and_then(|t| t.as_str()) == Some("anonymous") => {
+                    checks.push(CheckResult::fail(
+                        "authentication",
+                        "Credentials were not accepted (anonymous user)".to_string(),
+                        "Verify ATLASSIAN_USERNAME is your account email and regenerate ATLASSIAN_TOKEN",
+                    ));
+                }
+                Ok(user) => {
+                    let name = user
+                        .get("displayName")
+                        .or_else(|| user.get("email"))
+                        .and_then(|n| n.as_str())
+                        .unwrap_or("unknown user");
+                    checks.push(CheckResult::pass(
+                        "authentication",
+                        format!("Authenticated as {}", name),
+                    ));
+                }
+                Err(e) => {
+                    checks.push(CheckResult::fail(
+                        "authentication",
+                        format!("{:#}", e),
+                        "Verify ATLASSIAN_USERNAME is your account email and regenerate ATLASSIAN_TOKEN at https://id.atlassian.com/manage-profile/security/api-tokens",
+                    ));
+                }
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|c| !c.ok).count();
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&checks)?),
+        OutputFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(std::io::stdout());
+            for check in &checks {
+                wtr.serialize(check)?;
+            }
+            wtr.flush()?;
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            for check in &checks {
+                if check.ok {
+                    ui::print_success(&format!("{}: {}", check.check, check.detail));
+                } else {
+                    ui::print_error(&format!("{}: {}", check.check, check.detail));
+                    if let Some(hint) = &check.hint {
+                        ui::print_substep(hint);
+                    }
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} checks failed", failed, checks.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use crate::commands::{ActionResult, RunOptions};
+use crate::ui;
+use anyhow::Result;
+use clap::Args;
+use colored::Colorize;
+use ctag::api::{ConfluenceClient, TagPlan};
+use ctag::models::{ActionDetail, ProcessResults, TagResult};
+use std::time::Instant;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # Make every page in DOCS carry exactly these labels
+  ctag ensure 'space = DOCS' reviewed public
+
+  # Only add what's missing, keep any other labels
+  ctag ensure --additive-only 'space = DOCS' reviewed public
+
+  # See what would change on each page
+  ctag --dry-run ensure 'space = DOCS' reviewed public
+")]
+pub struct EnsureArgs {
+    /// CQL expression to match pages
+    pub cql_expression: String,
+
+    /// The labels each page should end up with
+    #[arg(required = true)]
+    pub tags: Vec<String>,
+
+    /// Only add missing labels; leave labels outside the set in place
+    #[arg(long)]
+    pub additive_only: bool,
+
+    /// Skip pages you don't have permission to edit instead of failing on them
+    #[arg(long)]
+    pub skip_no_edit: bool,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
+}
+
+/// Changes that bring `current` to exactly `desired` (or a superset of it
+/// with `additive_only`). An empty plan means the page already matches.
+fn ensure_plan(current: &[String], desired: &[String], additive_only: bool) -> TagPlan {
+    TagPlan {
+        add: desired
+            .iter()
+            .filter(|tag| !current.contains(tag))
+            .cloned()
+            .collect(),
+        remove: if additive_only {
+            Vec::new()
+        } else {
+            current
+                .iter()
+                .filter(|tag| !desired.contains(tag))
+                .cloned()
+                .collect()
+        },
+    }
+}
+
+pub fn run(
+    args: EnsureArgs,
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
+    let verbose = format.is_verbose();
+    if verbose {
+        ui::print_header("ENSURE TAGS");
+    }
+    let mut desired: Vec<String> = Vec::new();
+    for tag in &args.tags {
+        let tag = opts.normalize_tag(tag);
+        if !desired.contains(&tag) {
+            desired.push(tag);
+        }
+    }
+    let page_filter = args.query.page_filter()?;
+
+    let started = Instant::now();
+    let pages = args.query.find_pages(
+        client,
+        &args.cql_expression,
+        ctag::api::SEARCH_EXPAND,
+        format,
+        opts.show_progress,
+    )?;
+    let (pages, excluded) = crate::commands::exclude_pages(
+        client,
+        pages,
+        args.query.cql_exclude.as_deref(),
+        format,
+        opts.show_progress,
+    )?;
+    opts.timing.record("search", started);
+    let (pages, filtered) = page_filter.apply(pages);
+    let filtered = filtered + excluded;
+    let (pages, not_editable) =
+        crate::commands::retain_editable(client, pages, args.skip_no_edit, opts.read_concurrency);
+
+    if pages.is_empty() {
+        return Ok(opts.no_pages_found());
+    }
+    if verbose {
+        ui::print_info(&format!("Found {} matching pages.", pages.len()));
+        if filtered > 0 {
+            ui::print_info(&format!(
+                "Skipped {} pages filtered out by --title-match, --ancestor or --cql-exclude.",
+                filtered
+            ));
+        }
+    }
+
+    if opts.dry_run {
+        ui::print_dry_run("No changes will be made.");
+        let mut shown = 0;
+        let mut unchanged = 0;
+        for (i, page) in pages.iter().enumerate() {
+            if opts.preview_exhausted(shown) {
+                ui::print_preview_remainder(pages.len() - i);
+                break;
+            }
+            let Some(page_id) = page.page_id() else {
+                unchanged += 1;
+                continue;
+            };
+            let current = client.get_page_tags(page_id)?;
+            let plan = ensure_plan(&current, &desired, args.additive_only);
+            if plan.is_empty() {
+                unchanged += 1;
+                continue;
+            }
+            let display_title = page.printable_clickable_title(&client.web_base_url());
+            ui::print_page_action("Would update tags on", &display_title, page.space_name());
+            for tag in &plan.add {
+                ui::print_substep(&format!("{}: {}", "Add".green(), tag));
+            }
+            for tag in &plan.remove {
+                ui::print_substep(&format!("{}: {}", "Remove".red(), tag));
+            }
+            shown += 1;
+        }
+        return Ok(ProcessResults::preview(pages.len(), unchanged));
+    }
+
+    let started = Instant::now();
+    let mut results = crate::commands::process_pages_parallel(&pages, opts, |page| {
+        let Some(page_id) = page.page_id() else {
+            return ActionResult::Skipped;
+        };
+        let current = match client.get_page_tags(page_id) {
+            Ok(tags) => tags,
+            Err(_) => return ActionResult::Failed { detail: None },
+        };
+        let plan = ensure_plan(&current, &desired, args.additive_only);
+        // Already has exactly the desired labels: no_change
+        if plan.is_empty() {
+            return ActionResult::Skipped;
+        }
+        let removed = client.remove_tags(page_id, &plan.remove);
+        let added = client.add_tags(page_id, &plan.add);
+        let detail = ActionDetail {
+            page_id: page_id.to_string(),
+            title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+            space: page.space_name().to_string(),
+            url: page.printable_clickable_title(&client.web_base_url()),
+            tags_added: TagResult::succeeded(&added),
+            tags_removed: TagResult::succeeded(&removed),
+            tag_results: removed.into_iter().chain(added).collect(),
+        };
+        if !TagResult::all_succeeded(&detail.tag_results) {
+            return ActionResult::Failed {
+                detail: Some(detail),
+            };
+        }
+        ActionResult::Success {
+            added: detail.tags_added.len(),
+            removed: detail.tags_removed.len(),
+            detail: Some(detail),
+        }
+    });
+    opts.timing.record("apply", started);
+
+    // Pages removed by client-side filters count as skipped
+    results.total += filtered + not_editable;
+    results.skipped += filtered + not_editable;
+    results.record_pages(pages.iter().filter_map(|p| p.page_id()));
+
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_plan_converges_on_the_desired_set() {
+        let tags = |names: &[&str]| names.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let current = tags(&["a", "old"]);
+        let desired = tags(&["a", "b"]);
+
+        let plan = ensure_plan(&current, &desired, false);
+        assert_eq!(plan.add, tags(&["b"]));
+        assert_eq!(plan.remove, tags(&["old"]));
+
+        let additive = ensure_plan(&current, &desired, true);
+        assert_eq!(additive.add, tags(&["b"]));
+        assert!(additive.remove.is_empty());
+
+        assert!(ensure_plan(&desired, &desired, false).is_empty());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::commands::{ActionResult, RunOptions};
+use crate::ui;
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use ctag::api::ConfluenceClient;
+use ctag::models::{page_id_from_ref, ActionDetail, ProcessResults, SearchResultItem, TagResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Args)]
+#[command(after_help = "\
+CSV FORMAT:
+  page_id,action,tags,title
+  12345,add,reviewed;approved,Release notes
+  67890,remove,draft,
+  67890,replace,old-tag=new-tag,
+
+  page_id may also be a page URL copied from the browser.
+  action is add, remove or replace. Tags within a cell are separated by
+  ';'; replace entries are old=new pairs. Fields may be quoted, so titles
+  can contain commas, quotes and line breaks. The title column is
+  optional and only used for display. Rows for the same page are applied
+  together, in file order.
+
+EXAMPLES:
+  # Apply tag changes to explicit page ids
+  ctag from-csv changes.csv
+
+  # Preview the changes first
+  ctag --dry-run from-csv changes.csv
+
+  # Read a file exported with ';' between fields
+  ctag from-csv changes.csv --delimiter ';'
+")]
+pub struct FromCsvArgs {
+    /// CSV file with page_id, action, tags and optional title columns
+    pub csv_file: String,
+
+    /// Field delimiter of the input file, e.g. ';' for European Excel exports
+    #[arg(long, value_name = "CHAR", default_value_t = ',')]
+    pub delimiter: char,
+}
+
+/// Separator between tags within one `tags` cell
+const TAG_SEPARATOR: char = ';';
+
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    page_id: String,
+    action: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum TagOp {
+    Add(Vec<String>),
+    Remove(Vec<String>),
+    Replace(HashMap<String, String>),
+}
+
+/// All operations for one page, in file order
+#[derive(Debug)]
+struct PageOps {
+    page_id: String,
+    title: Option<String>,
+    ops: Vec<TagOp>,
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(TAG_SEPARATOR)
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_row(row: CsvRow) -> Result<(String, Option<String>, TagOp)> {
+    if row.page_id.trim().is_empty() {
+        anyhow::bail!("page_id is empty");
+    }
+    let page_id = page_id_from_ref(&row.page_id)?;
+    let tags = split_tags(&row.tags);
+    if tags.is_empty() {
+        anyhow::bail!("no tags given for page {}", page_id);
+    }
+    let op = match row.action.trim().to_lowercase().as_str() {
+        "add" => TagOp::Add(tags),
+        "remove" => TagOp::Remove(tags),
+        "replace" => TagOp::Replace(crate::commands::replace::parse_tag_pairs(&tags, false)?),
+        other => anyhow::bail!(
+            "unknown action '{}' (expected add, remove or replace)",
+            other
+        ),
+    };
+    let title = row
+        .title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+    Ok((page_id, title, op))
+}
+
+/// Parse CSV rows and group them by page, keeping first-seen page order.
+fn parse_csv<R: std::io::Read>(reader: R, delimiter: u8) -> Result<Vec<PageOps>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let mut pages: Vec<PageOps> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for (i, row) in rdr.deserialize::<CsvRow>().enumerate() {
+        // Line 1 is the header
+        let line = i + 2;
+        let row = row.context(format!("Invalid CSV on line {}", line))?;
+        let (page_id, title, op) = parse_row(row).context(format!("Line {}", line))?;
+        match index.get(&page_id) {
+            Some(&idx) => {
+                let page = &mut pages[idx];
+                if page.title.is_none() {
+                    page.title = title;
+                }
+                page.ops.push(op);
+            }
+            None => {
+                index.insert(page_id.clone(), pages.len());
+                pages.push(PageOps {
+                    page_id,
+                    title,
+                    ops: vec![op],
+                });
+            }
+        }
+    }
+    Ok(pages)
+}
+
+fn print_ops(ops: &[TagOp]) {
+    for op in ops {
+        match op {
+            TagOp::Add(tags) => {
+                for tag in tags {
+                    ui::print_substep(&format!("{}: {}", "Add".green(), tag));
+                }
+            }
+            TagOp::Remove(tags) => {
+                for tag in tags {
+                    ui::print_substep(&format!("{}: {}", "Remove".red(), tag));
+                }
+            }
+            TagOp::Replace(mapping) => {
+                for (old, new) in mapping {
+                    ui::print_substep(&format!("{}: {} -> {}", "Replace".yellow(), old, new));
+                }
+            }
+        }
+    }
+}
+
+pub fn run(
+    args: FromCsvArgs,
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
+    let verbose = format.is_verbose();
+    if verbose {
+        ui::print_header("EXECUTE FROM CSV");
+    }
+
+    if !args.delimiter.is_ascii() {
+        anyhow::bail!("--delimiter must be a single ASCII character");
+    }
+    let file = std::fs::File::open(&args.csv_file)
+        .context(format!("Failed to read CSV file: {}", args.csv_file))?;
+    let page_ops = parse_csv(file, args.delimiter as u8).context("Failed to parse CSV file")?;
+
+    if page_ops.is_empty() {
+        ui::print_warning("No rows found in the CSV file.");
+        return Ok(ProcessResults::new(0));
+    }
+    if verbose {
+        ui::print_info(&format!("Found {} pages in the CSV file.", page_ops.len()));
+    }
+
+    // Id-driven pages need no search; minimal results let them share the
+    // parallel runner and reporting with CQL-driven commands
+    let pages: Vec<SearchResultItem> = page_ops
+        .iter()
+        .map(|p| SearchResultItem::from_page_id(&p.page_id, p.title.clone()))
+        .collect();
+    let ops_by_id: HashMap<&str, &[TagOp]> = page_ops
+        .iter()
+        .map(|p| (p.page_id.as_str(), p.ops.as_slice()))
+        .collect();
+
+    if opts.dry_run {
+        ui::print_dry_run("No changes will be made.");
+        for (i, page) in pages.iter().enumerate() {
+            if opts.preview_exhausted(i) {
+                ui::print_preview_remainder(pages.len() - i);
+                break;
+            }
+            let page_id = page.page_id().unwrap_or_default();
+            let display_title = page.printable_clickable_title(&client.web_base_url());
+            ui::print_page_action("Would update tags on", &display_title, page_id);
+            print_ops(ops_by_id.get(page_id).copied().unwrap_or_default());
+        }
+        return Ok(ProcessResults::new(pages.len()));
+    }
+
+    let started = Instant::now();
+    let mut results = crate::commands::process_pages_parallel(&pages, opts, |page| {
+        let page_id = match page.page_id() {
+            Some(id) => id,
+            None => return ActionResult::Skipped,
+        };
+        let ops = ops_by_id.get(page_id).copied().unwrap_or_default();
+        let mut detail = ActionDetail {
+            page_id: page_id.to_string(),
+            title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+            space: page.space_name().to_string(),
+            url: page.printable_clickable_title(&client.web_base_url()),
+            tags_added: Vec::new(),
+            tags_removed: Vec::new(),
+            tag_results: Vec::new(),
+        };
+        for op in ops {
+            let ok = match op {
+                TagOp::Add(tags) => {
+                    let results = client.add_tags(page_id, tags);
+                    detail.tags_added.extend(TagResult::succeeded(&results));
+                    detail.tag_results.extend(results);
+                    TagResult::all_succeeded(&detail.tag_results)
+                }
+                TagOp::Remove(tags) => {
+                    let results = client.remove_tags(page_id, tags);
+                    detail.tags_removed.extend(TagResult::succeeded(&results));
+                    detail.tag_results.extend(results);
+                    TagResult::all_succeeded(&detail.tag_results)
+                }
+                TagOp::Replace(mapping) => {
+                    let mapping: HashMap<String, String> = mapping
+                        .iter()
+                        .map(|(old, new)| (opts.normalize_tag(old), opts.normalize_tag(new)))
+                        .collect();
+                    match client.replace_tags(page_id, &mapping) {
+                        Some(applied) => {
+                            for (old, new) in applied {
+                                detail.tags_removed.push(old);
+                                detail.tags_added.push(new);
+                            }
+                            true
+                        }
+                        None => false,
+                    }
+                }
+            };
+            if !ok {
+                return ActionResult::Failed {
+                    detail: Some(detail),
+                };
+            }
+        }
+        ActionResult::Success {
+            added: detail.tags_added.len(),
+            removed: detail.tags_removed.len(),
+            detail: Some(detail),
+        }
+    });
+    opts.timing.record("apply", started);
+    results.record_pages(pages.iter().filter_map(|p| p.page_id()));
+
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_groups_rows_by_page() {
+        let csv = "page_id,action,tags,title\n\
+                   1,add,a; b,First\n\
+                   2,remove,c,\n\
+                   https://x.atlassian.net/wiki/spaces/D/pages/1/First,replace,old=new,\n";
+        let pages = parse_csv(csv.as_bytes(), b',').unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].page_id, "1");
+        assert_eq!(pages[0].title.as_deref(), Some("First"));
+        assert_eq!(
+            pages[0].ops[0],
+            TagOp::Add(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            pages[0].ops[1],
+            TagOp::Replace(HashMap::from([("old".to_string(), "new".to_string())]))
+        );
+        assert_eq!(pages[1].title, None);
+        assert_eq!(pages[1].ops, vec![TagOp::Remove(vec!["c".to_string()])]);
+    }
+
+    #[test]
+    fn parse_csv_handles_quoted_titles_and_multi_tag_cells() {
+        let csv = "page_id,action,tags,title\n\
+                   1,add,\"reviewed; approved;final\",\"Notes, \"\"final\"\" draft\"\n\
+                   2,remove,\"old\",\"Line one\nline two\"\n";
+        let pages = parse_csv(csv.as_bytes(), b',').unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].title.as_deref(), Some("Notes, \"final\" draft"));
+        assert_eq!(
+            pages[0].ops,
+            vec![TagOp::Add(vec![
+                "reviewed".to_string(),
+                "approved".to_string(),
+                "final".to_string()
+            ])]
+        );
+        assert_eq!(pages[1].title.as_deref(), Some("Line one\nline two"));
+
+        // With ';' as the field delimiter the tag cell must be quoted
+        let csv = "page_id;action;tags;title\n1;add;\"a;b\";Notes, v2\n";
+        let pages = parse_csv(csv.as_bytes(), b';').unwrap();
+        assert_eq!(pages[0].title.as_deref(), Some("Notes, v2"));
+        assert_eq!(
+            pages[0].ops,
+            vec![TagOp::Add(vec!["a".to_string(), "b".to_string()])]
+        );
+    }
+
+    #[test]
+    fn parse_csv_reports_line_of_bad_row() {
+        let csv = "page_id,action,tags\n1,add,a\n2,rename,b\n";
+        let err = parse_csv(csv.as_bytes(), b',').unwrap_err();
+        assert!(format!("{:#}", err).contains("Line 3"));
+    }
+}
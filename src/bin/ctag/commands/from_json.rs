@@ -1,16 +1,20 @@
+use crate::commands::RunOptions;
 use crate::ui;
 use anyhow::{Context, Result};
 use clap::Args;
 use ctag::api::ConfluenceClient;
-use ctag::models::{OutputFormat, ProcessResults};
+use ctag::models::{ProcessResults, SearchResultItem};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::fs;
 
 #[derive(Args)]
 pub struct FromJsonArgs {
-    /// JSON file containing commands
-    pub json_file: String,
+    /// JSON files containing commands, run in the order given
+    #[arg(required = true)]
+    pub json_files: Vec<String>,
 
     /// Key to abort all operations in interactive mode
     #[arg(long, default_value = "q")]
@@ -41,75 +45,125 @@ pub(crate) struct JsonCommand {
 pub fn run(
     args: FromJsonArgs,
     client: &ConfluenceClient,
-    dry_run: bool,
-    progress: bool,
-    format: OutputFormat,
-) -> Result<()> {
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
     let verbose = format.is_verbose();
     let is_structured = format.is_structured();
     if verbose {
         ui::print_header("EXECUTE FROM JSON");
     }
-    // Read and parse JSON file
-    let json_content = fs::read_to_string(&args.json_file)
-        .context(format!("Failed to read JSON file: {}", args.json_file))?;
-
-    let json_commands: JsonCommands =
-        serde_json::from_str(&json_content).context("Failed to parse JSON file")?;
-
-    if let Some(desc) = &json_commands.description {
+    // Parse every file up front so a typo in the last one fails before
+    // the first one has changed anything
+    let mut files: Vec<(&str, JsonCommands)> = Vec::new();
+    for path in &args.json_files {
+        let json_content =
+            fs::read_to_string(path).context(format!("Failed to read JSON file: {}", path))?;
+        let json_commands: JsonCommands = serde_json::from_str(&json_content)
+            .context(format!("Failed to parse JSON file: {}", path))?;
         if verbose {
-            ui::print_info(&format!("Description: {}", desc));
+            if let Some(desc) = &json_commands.description {
+                ui::print_info(&format!("Description: {}", desc));
+            }
+            ui::print_info(&format!(
+                "Found {} commands in {}.",
+                json_commands.commands.len(),
+                path
+            ));
         }
+        files.push((path, json_commands));
     }
 
-    if verbose {
-        ui::print_info(&format!(
-            "Found {} commands in the JSON file.",
-            json_commands.commands.len()
-        ));
+    if opts.dry_run {
+        let commands: Vec<JsonCommand> = files
+            .into_iter()
+            .flat_map(|(_, json_commands)| json_commands.commands)
+            .collect();
+        return plan_commands(&commands, client, opts);
     }
 
-    let mut results = ProcessResults::new(json_commands.commands.len());
+    let total = files.iter().map(|(_, f)| f.commands.len()).sum();
+    let mut results = ProcessResults::new(total);
 
-    for (i, command) in json_commands.commands.iter().enumerate() {
-        if verbose {
-            ui::print_step(&format!(
-                "Command {}/{}: {} on {}",
-                i + 1,
-                json_commands.commands.len(),
-                command.action.to_uppercase(),
-                command.cql_expression
-            ));
-        }
-
-        match process_single_command(command, client, dry_run, progress, format, &args.abort_key) {
-            Ok(_) => {
-                results.processed += 1;
-                results.success += 1;
+    // Aborting or hitting the deadline stops the whole set; a failed
+    // command moves on to the next one, in the same file or the next
+    'files: for (path, json_commands) in &files {
+        let mut file_results = ProcessResults::new(json_commands.commands.len());
+        for (i, command) in json_commands.commands.iter().enumerate() {
+            if opts.deadline_passed() {
+                results.deadline_reached = true;
+                break;
+            }
+            if verbose {
+                ui::print_step(&format!(
+                    "{} command {}/{}: {} on {}",
+                    path,
+                    i + 1,
+                    json_commands.commands.len(),
+                    command.action.to_uppercase(),
+                    command.cql_expression
+                ));
             }
-            Err(e) => {
-                results.processed += 1;
-                results.failed += 1;
-                if verbose || !is_structured {
-                    ui::print_error(&format!("Command failed: {}", e));
+
+            match process_single_command(command, client, opts, &args.abort_key) {
+                Ok(sub) => {
+                    file_results.processed += 1;
+                    file_results.success += 1;
+                    file_results.record_pages(sub.page_ids.iter().map(String::as_str));
+                    if sub.aborted {
+                        results.aborted = true;
+                        break;
+                    }
+                    if sub.deadline_reached {
+                        results.deadline_reached = true;
+                        break;
+                    }
+                }
+                Err(e) => {
+                    file_results.processed += 1;
+                    file_results.failed += 1;
+                    if verbose || !is_structured {
+                        ui::print_error(&format!("Command failed: {}", e));
+                    }
                 }
             }
         }
+        merge_file_results(&mut results, path, file_results);
+        if results.aborted || results.deadline_reached {
+            break 'files;
+        }
     }
+    // A single file's subtotal would only repeat the summary
+    if files.len() == 1 {
+        results.subtotals.clear();
+    }
+
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
+}
 
-    ui::print_summary(&results, format);
-    Ok(())
+/// Fold one file's counts into the combined results, keeping a subtotal for it
+fn merge_file_results(results: &mut ProcessResults, path: &str, file: ProcessResults) {
+    results.processed += file.processed;
+    results.success += file.success;
+    results.failed += file.failed;
+    results.record_pages(file.page_ids.iter().map(String::as_str));
+    results.subtotals.push(ctag::models::Subtotal {
+        source: path.to_string(),
+        total: file.total,
+        processed: file.processed,
+        success: file.success,
+        failed: file.failed,
+        distinct_pages: file.distinct_pages,
+    });
 }
 
 pub(crate) fn process_single_command(
     command: &JsonCommand,
     client: &ConfluenceClient,
-    dry_run: bool,
-    progress: bool,
-    format: OutputFormat,
+    opts: &RunOptions,
     abort_key: &str,
-) -> Result<()> {
+) -> Result<ProcessResults> {
     match command.action.as_str() {
         "add" => {
             let tags_value = command
@@ -123,8 +177,16 @@ pub(crate) fn process_single_command(
                 tags,
                 interactive: command.interactive,
                 abort_key: abort_key.to_string(),
+                confirm_each_space: false,
+                stdin_tags: false,
+                rules: None,
+                atomic: false,
+                skip_no_edit: false,
+                review: false,
+                verify_after: None,
+                query: crate::commands::QueryArgs::default(),
             };
-            crate::commands::add::run(add_args, client, dry_run, progress, format)
+            crate::commands::add::run(add_args, client, opts)
         }
         "remove" => {
             let tags_value = command
@@ -138,9 +200,15 @@ pub(crate) fn process_single_command(
                 tags,
                 interactive: command.interactive,
                 abort_key: abort_key.to_string(),
+                confirm_each_space: false,
                 regex: command.regex,
+                stdin_tags: false,
+                skip_no_edit: false,
+                review: false,
+                verify_after: None,
+                query: crate::commands::QueryArgs::default(),
             };
-            crate::commands::remove::run(remove_args, client, dry_run, progress, format)
+            crate::commands::remove::run(remove_args, client, opts)
         }
         "replace" => {
             let tags_value = command
@@ -154,14 +222,256 @@ pub(crate) fn process_single_command(
                 tag_pairs,
                 interactive: command.interactive,
                 abort_key: abort_key.to_string(),
+                confirm_each_space: false,
                 regex: command.regex,
+                swap: false,
+                add_if_missing: false,
+                verify: false,
+                skip_no_edit: false,
+                review: false,
+                verify_after: None,
+                query: crate::commands::QueryArgs::default(),
             };
-            crate::commands::replace::run(replace_args, client, dry_run, progress, format)
+            crate::commands::replace::run(replace_args, client, opts)
         }
         _ => anyhow::bail!("Unknown action: {}", command.action),
     }
 }
 
+/// One command's effect on a page's tags, for the dry-run plan
+enum PlanStep {
+    Add(Vec<String>),
+    Remove(Vec<String>),
+    RemoveRegex(Vec<Regex>),
+    Replace(HashMap<String, String>),
+    ReplaceRegex(Vec<(Regex, String)>),
+}
+
+impl PlanStep {
+    fn from_command(command: &JsonCommand, opts: &RunOptions) -> Result<Self> {
+        let tags = command.tags.as_ref().context(format!(
+            "'tags' field required for '{}' action",
+            command.action
+        ))?;
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|e| anyhow::anyhow!("Invalid regex '{}': {}", pattern, e))
+        };
+        Ok(match command.action.as_str() {
+            "add" => PlanStep::Add(
+                parse_add_remove_tags(tags, "add")?
+                    .iter()
+                    .map(|tag| opts.normalize_tag(tag))
+                    .collect(),
+            ),
+            "remove" if command.regex => PlanStep::RemoveRegex(
+                parse_add_remove_tags(tags, "remove")?
+                    .iter()
+                    .map(|t| compile(t))
+                    .collect::<Result<_>>()?,
+            ),
+            "remove" => PlanStep::Remove(parse_add_remove_tags(tags, "remove")?),
+            "replace" => {
+                let pairs = parse_replace_tag_pairs(tags, command.regex)?;
+                let mapping = crate::commands::replace::parse_tag_pairs(&pairs, command.regex)?;
+                if command.regex {
+                    PlanStep::ReplaceRegex(
+                        mapping
+                            .into_iter()
+                            .map(|(old, new)| Ok((compile(&old)?, new)))
+                            .collect::<Result<_>>()?,
+                    )
+                } else {
+                    PlanStep::Replace(
+                        mapping
+                            .into_iter()
+                            .map(|(old, new)| (opts.normalize_tag(&old), opts.normalize_tag(&new)))
+                            .collect(),
+                    )
+                }
+            }
+            _ => anyhow::bail!("Unknown action: {}", command.action),
+        })
+    }
+
+    /// Tags a page would have after this step
+    fn apply(&self, tags: &[String]) -> Vec<String> {
+        let mut tags = tags.to_vec();
+        match self {
+            PlanStep::Add(add) => {
+                for tag in add {
+                    if !tags.contains(tag) {
+                        tags.push(tag.clone());
+                    }
+                }
+            }
+            PlanStep::Remove(remove) => tags.retain(|tag| !remove.contains(tag)),
+            PlanStep::RemoveRegex(regexes) => {
+                tags.retain(|tag| !regexes.iter().any(|re| re.is_match(tag)))
+            }
+            PlanStep::Replace(mapping) => {
+                let plan = ctag::api::plan_replacements(&tags, mapping);
+                tags.retain(|tag| !plan.remove.contains(tag));
+                tags.extend(plan.add);
+            }
+            PlanStep::ReplaceRegex(pairs) => {
+                let mapping = ctag::api::compute_replacements_by_regex(tags.clone(), pairs);
+                return PlanStep::Replace(mapping).apply(&tags);
+            }
+        }
+        tags
+    }
+}
+
+/// A page touched by the plan, with its tags before and after all commands
+struct PlannedPage {
+    page: SearchResultItem,
+    before: Vec<String>,
+    after: Vec<String>,
+}
+
+impl PlannedPage {
+    fn added(&self) -> Vec<String> {
+        let mut added: Vec<String> = self
+            .after
+            .iter()
+            .filter(|tag| !self.before.contains(tag))
+            .cloned()
+            .collect();
+        added.sort();
+        added
+    }
+
+    fn removed(&self) -> Vec<String> {
+        let mut removed: Vec<String> = self
+            .before
+            .iter()
+            .filter(|tag| !self.after.contains(tag))
+            .cloned()
+            .collect();
+        removed.sort();
+        removed
+    }
+}
+
+/// Net change to one page across every command in the file
+#[derive(Serialize)]
+struct PlannedChange<'a> {
+    id: &'a str,
+    title: &'a str,
+    space: &'a str,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+/// Dry run for a whole file: simulate the commands in order against each
+/// page's current tags and print one consolidated diff. CQL is evaluated
+/// against Confluence as it is now, not as earlier commands would leave it.
+pub(crate) fn plan_commands(
+    commands: &[JsonCommand],
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
+    let steps = commands
+        .iter()
+        .map(|command| PlanStep::from_command(command, opts))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut planned: Vec<PlannedPage> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut matches = 0;
+    for (command, step) in commands.iter().zip(&steps) {
+        let pages = crate::commands::get_matching_pages(
+            client,
+            &command.cql_expression,
+            100,
+            format,
+            opts.show_progress,
+        )?;
+        for page in pages {
+            let Some(page_id) = page.page_id().map(str::to_string) else {
+                continue;
+            };
+            matches += 1;
+            let i = match index.get(&page_id) {
+                Some(&i) => i,
+                None => {
+                    let before = client.get_page_tags(&page_id)?;
+                    planned.push(PlannedPage {
+                        page,
+                        after: before.clone(),
+                        before,
+                    });
+                    index.insert(page_id, planned.len() - 1);
+                    planned.len() - 1
+                }
+            };
+            planned[i].after = step.apply(&planned[i].after);
+        }
+    }
+
+    let changes: Vec<PlannedChange> = planned
+        .iter()
+        .map(|p| PlannedChange {
+            id: p.page.page_id().unwrap_or_default(),
+            title: p.page.title.as_deref().unwrap_or("Unknown"),
+            space: p.page.space_name(),
+            added: p.added(),
+            removed: p.removed(),
+        })
+        .filter(|c| !c.added.is_empty() || !c.removed.is_empty())
+        .collect();
+
+    match format {
+        ctag::models::OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&changes)?)
+        }
+        ctag::models::OutputFormat::Csv => {
+            #[derive(Serialize)]
+            struct PlannedChangeCsv<'a> {
+                id: &'a str,
+                title: &'a str,
+                space: &'a str,
+                added: String,
+                removed: String,
+            }
+            let mut wtr = opts.csv.writer(std::io::stdout())?;
+            for change in &changes {
+                wtr.serialize(PlannedChangeCsv {
+                    id: change.id,
+                    title: change.title,
+                    space: change.space,
+                    added: change.added.join(", "),
+                    removed: change.removed.join(", "),
+                })?;
+            }
+            wtr.flush()?;
+        }
+        ctag::models::OutputFormat::Simple | ctag::models::OutputFormat::Verbose => {
+            ui::print_dry_run("No changes will be made. Net changes across all commands:");
+            for (i, change) in changes.iter().enumerate() {
+                if opts.preview_exhausted(i) {
+                    ui::print_preview_remainder(changes.len() - i);
+                    break;
+                }
+                let path = format!("{} ({})", change.title, change.space);
+                ui::print_tag_diff(&path, &change.added, &change.removed);
+            }
+            ui::print_info(&format!(
+                "{} pages would change: {} tags added, {} removed ({} pages matched).",
+                changes.len(),
+                changes.iter().map(|c| c.added.len()).sum::<usize>(),
+                changes.iter().map(|c| c.removed.len()).sum::<usize>(),
+                planned.len()
+            ));
+        }
+    }
+
+    let mut results = ProcessResults::new(matches);
+    results.record_pages(planned.iter().filter_map(|p| p.page.page_id()));
+    Ok(results)
+}
+
 /// Parse the `tags` value for add/remove actions as an array of strings.
 pub(crate) fn parse_add_remove_tags(value: &Value, action: &str) -> Result<Vec<String>> {
     match value {
@@ -230,6 +540,32 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn merge_file_results_sums_counts_and_keeps_subtotals() {
+        let mut results = ProcessResults::new(5);
+        let mut first = ProcessResults::new(2);
+        first.processed = 2;
+        first.success = 2;
+        first.record_pages(["1", "2"]);
+        let mut second = ProcessResults::new(3);
+        second.processed = 3;
+        second.success = 2;
+        second.failed = 1;
+        second.record_pages(["2", "3"]);
+
+        merge_file_results(&mut results, "a.json", first);
+        merge_file_results(&mut results, "b.json", second);
+        assert_eq!(
+            (results.processed, results.success, results.failed),
+            (5, 4, 1)
+        );
+        assert_eq!(results.distinct_pages, 3);
+        assert_eq!(results.subtotals.len(), 2);
+        assert_eq!(results.subtotals[1].source, "b.json");
+        assert_eq!(results.subtotals[1].failed, 1);
+        assert_eq!(results.subtotals[1].distinct_pages, 2);
+    }
+
     #[test]
     fn parse_add_remove_tags_valid_array() {
         let value = json!(["a", "b"]);
@@ -245,6 +581,27 @@ mod tests {
         assert_eq!(pairs, vec!["foo=bar".to_string(), "old=new".to_string()]);
     }
 
+    #[test]
+    fn plan_steps_net_out_across_commands() {
+        let add = PlanStep::Add(vec!["draft".to_string(), "q4".to_string()]);
+        let replace =
+            PlanStep::Replace(HashMap::from([("draft".to_string(), "final".to_string())]));
+        let remove = PlanStep::RemoveRegex(vec![Regex::new("^q[0-9]$").unwrap()]);
+
+        let before = vec!["q4".to_string(), "keep".to_string()];
+        let mut after = before.clone();
+        for step in [&add, &replace, &remove] {
+            after = step.apply(&after);
+        }
+        let page = PlannedPage {
+            page: SearchResultItem::from_page_id("1", None),
+            before,
+            after,
+        };
+        assert_eq!(page.added(), vec!["final"]);
+        assert_eq!(page.removed(), vec!["q4"]);
+    }
+
     #[test]
     fn parse_replace_tag_pairs_regex_mode() {
         let value = json!({"test-.*": "new-test", "id-[0-9]+": "matched-id"});
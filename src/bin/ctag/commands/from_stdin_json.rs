@@ -1,4 +1,5 @@
-use crate::commands::from_json::{process_single_command, JsonCommands};
+use crate::commands::from_json::{plan_commands, process_single_command, JsonCommands};
+use crate::commands::RunOptions;
 use crate::ui;
 use anyhow::{Context, Result};
 use clap::Args;
@@ -16,10 +17,9 @@ pub struct FromStdinJsonArgs {
 pub fn run(
     args: FromStdinJsonArgs,
     client: &ConfluenceClient,
-    dry_run: bool,
-    progress: bool,
-    format: OutputFormat,
-) -> Result<()> {
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
     let verbose = format == OutputFormat::Verbose;
     let is_structured = format == OutputFormat::Json || format == OutputFormat::Csv;
 
@@ -54,9 +54,17 @@ pub fn run(
         ));
     }
 
+    if opts.dry_run {
+        return plan_commands(&json_commands.commands, client, opts);
+    }
+
     let mut results = ProcessResults::new(json_commands.commands.len());
 
     for (i, command) in json_commands.commands.iter().enumerate() {
+        if opts.deadline_passed() {
+            results.deadline_reached = true;
+            break;
+        }
         if verbose {
             ui::print_step(&format!(
                 "Command {}/{}: {} on {}",
@@ -67,10 +75,19 @@ pub fn run(
             ));
         }
 
-        match process_single_command(command, client, dry_run, progress, format, &args.abort_key) {
-            Ok(_) => {
+        match process_single_command(command, client, opts, &args.abort_key) {
+            Ok(sub) => {
                 results.processed += 1;
                 results.success += 1;
+                results.record_pages(sub.page_ids.iter().map(String::as_str));
+                if sub.aborted {
+                    results.aborted = true;
+                    break;
+                }
+                if sub.deadline_reached {
+                    results.deadline_reached = true;
+                    break;
+                }
             }
             Err(e) => {
                 results.processed += 1;
@@ -82,6 +99,6 @@ pub fn run(
         }
     }
 
-    ui::print_summary(&results, format);
-    Ok(())
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
 }
@@ -1,13 +1,21 @@
+use crate::commands::RunOptions;
 use crate::ui;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
+use colored::Colorize;
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::{Attribute, Cell, Color, Table};
+use ctag::aliases::TagAliases;
 use ctag::api::{sanitize_text, ConfluenceClient};
-use ctag::models::OutputFormat;
-use serde::Serialize;
-use std::collections::HashSet;
+use ctag::models::{page_id_from_ref, OutputFormat, ProcessResults, SearchResultItem};
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
 #[derive(Args)]
 #[command(after_help = "\
@@ -21,19 +29,69 @@ EXAMPLES:
   # Output as JSON
   ctag get 'space = DOCS' --format json
 
+  # Output as JSON nested by space and parent pages
+  ctag get 'space = DOCS' --format json --tree
+
+  # Show the page tree without the rest of the verbose output
+  ctag get 'space = DOCS' --tree
+
   # Save results to a file
   ctag get 'space = DOCS' --output-file results.json
 
+  # List the pages carrying each tag
+  ctag get 'label = deprecated' --by-tag
+
   # Get tags from recently modified pages
   ctag get 'space = DOCS AND lastmodified > -30d'
 
   # Get tags in CSV format
   ctag get 'label = migration' --format csv --output-file migration-tags.csv
+
+  # Export one JSON file per space plus a manifest.json index
+  ctag get 'type = page' --format json --output-dir export/
+
+  # Export just the matching page ids, one per line
+  ctag get 'space = DOCS AND lastmodified > -7d' --ids-only > ids.txt
+
+  # Print tags shared by every page once instead of on every line
+  ctag get 'space = DOCS' --collapse-common-tags
+
+  # Report pages whose tags changed since an earlier JSON export
+  ctag get 'space = DOCS' --format json --output-file before.json
+  ctag get 'space = DOCS' --baseline before.json --format json
+
+  # Find pages that have no labels at all
+  ctag get 'space = DOCS' --untagged
+
+  # Read known pages directly, without waiting for the search index
+  ctag get --page-ids 12345,67890
+
+  # Tell team and personal labels apart from global ones
+  ctag get 'space = DOCS' --detailed
+
+  # Stream a very large space to CSV, 5000 pages at a time
+  ctag get 'space = ARCHIVE' --format csv --output-file archive.csv --max-inflight-pages 5000
 ")]
 pub struct GetArgs {
     /// CQL expression to match pages
+    #[arg(required_unless_present = "page_ids", default_value = "")]
     pub cql_expression: String,
 
+    /// Read these pages (ids or page URLs, comma-separated) directly instead
+    /// of searching, so recently changed pages show up without index lag
+    #[arg(
+        long,
+        value_name = "IDS",
+        value_delimiter = ',',
+        conflicts_with = "cql_expression"
+    )]
+    pub page_ids: Vec<String>,
+
+    /// Print each page's label objects exactly as Confluence returns them, as
+    /// JSON, instead of the usual output. For debugging label problems
+    #[arg(long, requires = "page_ids", conflicts_with_all = ["tags_only", "case_report", "tree", "ids_only", "output_dir", "baseline"])]
+    pub raw_labels: bool,
+
     /// Include page titles and spaces in output
     #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
     pub show_pages: bool,
@@ -42,6 +100,21 @@ pub struct GetArgs {
     #[arg(long)]
     pub tags_only: bool,
 
+    /// Only list labels that collide once case and surrounding whitespace are
+    /// ignored, grouped by their normalized form
+    #[arg(long, conflicts_with_all = ["tags_only", "tree", "ids_only", "output_dir", "baseline", "max_inflight_pages"])]
+    pub case_report: bool,
+
+    /// List each tag with the pages carrying it, instead of each page with
+    /// its tags
+    #[arg(long, conflicts_with_all = ["tags_only", "case_report", "tree", "flat", "ids_only", "output_dir", "baseline", "max_inflight_pages", "collapse_common_tags"])]
+    pub by_tag: bool,
+
+    /// Fold aliased labels into their canonical name in --tags-only output,
+    /// using a TOML file of `canonical = ["alias", ...]` entries
+    #[arg(long, value_name = "FILE", requires = "tags_only")]
+    pub alias_file: Option<String>,
+
     /// Browse results interactively
     #[arg(long)]
     pub interactive: bool,
@@ -53,72 +126,539 @@ pub struct GetArgs {
     /// Save results to file
     #[arg(long)]
     pub output_file: Option<String>,
+
+    /// Show pages as a tree of spaces and ancestor pages: nested objects in
+    /// JSON, box-drawing connectors in text output (the verbose default)
+    #[arg(long)]
+    pub tree: bool,
+
+    /// List text output as one path per page, also with --verbose
+    #[arg(long, conflicts_with = "tree")]
+    pub flat: bool,
+
+    /// Hold at most N pages in memory; larger result sets are written
+    /// incrementally (CSV page output only)
+    #[arg(long, value_name = "N")]
+    pub max_inflight_pages: Option<usize>,
+
+    /// Write one file per space into this directory, plus a manifest.json
+    /// indexing them (requires --format json or csv)
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["output_file", "tags_only", "tree", "max_inflight_pages", "baseline"]
+    )]
+    pub output_dir: Option<String>,
+
+    /// Print only the ids of matching pages, without fetching their tags
+    #[arg(
+        long,
+        conflicts_with_all = ["tags_only", "tree", "max_inflight_pages", "collapse_common_tags", "baseline", "output_dir"]
+    )]
+    pub ids_only: bool,
+
+    /// Print tags shared by every page once, and only each page's other tags
+    /// (default and verbose page output)
+    #[arg(long, conflicts_with_all = ["tags_only", "tree"])]
+    pub collapse_common_tags: bool,
+
+    /// Only output pages whose tags changed since this earlier
+    /// `get --format json` export
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tags_only", "tree", "max_inflight_pages"])]
+    pub baseline: Option<String>,
+
+    /// Only output pages with no tags at all
+    #[arg(long, conflicts_with_all = ["tagged", "ids_only"])]
+    pub untagged: bool,
+
+    /// Only output pages with at least one tag
+    #[arg(long, conflicts_with = "ids_only")]
+    pub tagged: bool,
+
+    /// Show each tag with its prefix (`team:onboarding`, `my:todo`); global
+    /// tags are shown without one
+    #[arg(long, conflicts_with = "ids_only")]
+    pub detailed: bool,
+
+    /// Include each page's creation date, last modification and version
+    /// number in page output
+    #[arg(long, conflicts_with_all = ["ids_only", "tags_only", "case_report"])]
+    pub with_history: bool,
+
+    /// Keep each page's labels in the order Confluence returns them (roughly
+    /// the order they were added) instead of sorting them; tag lists across
+    /// pages follow first appearance
+    #[arg(long)]
+    pub preserve_tag_order: bool,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
+}
+
+/// Keep pages whose tag count is within `min..=max`
+#[derive(Debug, Default, Clone, Copy)]
+struct TagCountFilter {
+    min: usize,
+    max: Option<usize>,
+}
+
+impl TagCountFilter {
+    fn from_args(args: &GetArgs) -> Self {
+        if args.untagged {
+            Self {
+                min: 0,
+                max: Some(0),
+            }
+        } else if args.tagged {
+            Self { min: 1, max: None }
+        } else {
+            Self::default()
+        }
+    }
+
+    fn keeps(&self, page: &PageData) -> bool {
+        let count = page.tags.len();
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
 }
 
 #[derive(Serialize)]
-struct PageData {
+pub(crate) struct PageData {
     id: String,
     title: String,
-    space: String,
-    tags: Vec<String>,
+    pub(crate) space: String,
+    /// Space key for building follow-up CQL; the name is for display
+    #[serde(skip_serializing_if = "Option::is_none")]
+    space_key: Option<String>,
+    pub(crate) tags: Vec<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     ancestors: Vec<String>,
     url: String,
+    /// Content type when it is not a page, e.g. `attachment`
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    /// Creation and last-modified details, with `--with-history`
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    history: Option<PageHistory>,
 }
 
-pub fn run(
-    args: GetArgs,
-    client: &ConfluenceClient,
-    show_progress: bool,
-    format: OutputFormat,
-) -> Result<()> {
+/// When a page was created and last changed, for `--with-history`
+#[derive(Serialize, Default)]
+struct PageHistory {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version_number: Option<u64>,
+}
+
+impl From<&ctag::models::Content> for PageHistory {
+    fn from(content: &ctag::models::Content) -> Self {
+        Self {
+            created_date: content
+                .history
+                .as_ref()
+                .and_then(|h| h.created_date.clone()),
+            last_modified: content.version.as_ref().and_then(|v| v.when.clone()),
+            version_number: content.version.as_ref().and_then(|v| v.number),
+        }
+    }
+}
+
+impl PageHistory {
+    /// ` (v3, modified 2024-05-01T10:00:00.000Z)` for text output
+    fn note(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(number) = self.version_number {
+            parts.push(format!("v{}", number));
+        }
+        if let Some(when) = &self.last_modified {
+            parts.push(format!("modified {}", when));
+        }
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", parts.join(", "))
+        }
+    }
+}
+
+/// One CSV row of page output
+#[derive(Serialize)]
+struct PageDataCsv<'a> {
+    id: &'a str,
+    path: String,
+    space: &'a str,
+    space_key: &'a str,
+    tags: String,
+    url: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_date: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version_number: Option<String>,
+}
+
+impl<'a> From<&'a PageData> for PageDataCsv<'a> {
+    fn from(page: &'a PageData) -> Self {
+        Self {
+            id: &page.id,
+            path: build_page_path(&page.space, &page.ancestors, &page.title),
+            space: &page.space,
+            space_key: page.space_key.as_deref().unwrap_or_default(),
+            tags: page.tags.join(", "),
+            url: &page.url,
+            // Always all three with --with-history, so every row has the same columns
+            created_date: page
+                .history
+                .as_ref()
+                .map(|h| h.created_date.as_deref().unwrap_or_default()),
+            last_modified: page
+                .history
+                .as_ref()
+                .map(|h| h.last_modified.as_deref().unwrap_or_default()),
+            version_number: page
+                .history
+                .as_ref()
+                .map(|h| h.version_number.map(|n| n.to_string()).unwrap_or_default()),
+        }
+    }
+}
+
+pub fn run(args: GetArgs, client: &ConfluenceClient, opts: &RunOptions) -> Result<ProcessResults> {
+    let format = opts.format;
     let verbose = format.is_verbose();
     let is_structured = format.is_structured();
     if verbose {
         ui::print_header("GET TAGS");
     }
+    let page_filter = args.query.page_filter()?;
+    if args.tree && format == OutputFormat::Csv {
+        anyhow::bail!("--tree doesn't apply to --format csv");
+    }
+    if args.flat && is_structured {
+        anyhow::bail!("--flat applies to simple and verbose output");
+    }
+    let listing_format = listing_format(format, args.tree, args.flat);
+    if args.output_dir.is_some() && !is_structured {
+        anyhow::bail!("--output-dir requires --format json or csv");
+    }
+    let baseline = match &args.baseline {
+        Some(path) => {
+            if args.output_file.is_some() && !is_structured {
+                anyhow::bail!("--baseline with --output-file requires --format json or csv");
+            }
+            Some(load_baseline(path)?)
+        }
+        None => None,
+    };
+    let aliases = args
+        .alias_file
+        .as_deref()
+        .map(TagAliases::from_file)
+        .transpose()?;
+
+    if args.raw_labels {
+        return print_raw_labels(&args.page_ids, client, opts);
+    }
+
     // Get matching pages
-    let pages = crate::commands::get_matching_pages(
+    let started = Instant::now();
+    let pages = if args.page_ids.is_empty() {
+        // Listing ids needs no space, label or version expansion, unless
+        // --ancestor has to see each result's ancestors
+        let expand = if args.ids_only && args.query.ancestor.is_none() {
+            ctag::api::SEARCH_EXPAND_NONE
+        } else if args.with_history {
+            ctag::api::SEARCH_EXPAND_HISTORY
+        } else {
+            ctag::api::SEARCH_EXPAND
+        };
+        args.query.find_pages(
+            client,
+            &args.cql_expression,
+            expand,
+            format,
+            opts.show_progress,
+        )?
+    } else {
+        fetch_pages_by_id(&args.page_ids, client, opts)?
+    };
+    let (pages, excluded) = crate::commands::exclude_pages(
         client,
-        &args.cql_expression,
-        100,
+        pages,
+        args.query.cql_exclude.as_deref(),
         format,
-        show_progress,
+        opts.show_progress,
     )?;
+    opts.timing.record("search", started);
+    let (pages, filtered) = page_filter.apply(pages);
+    let filtered = filtered + excluded;
 
     if pages.is_empty() {
         match format {
+            OutputFormat::Json if args.case_report => println!("{{}}"),
             OutputFormat::Json => println!("[]"),
-            OutputFormat::Csv => println!(), // Empty CSV
-            _ => ui::print_warning("No pages found matching the CQL expression."),
+            OutputFormat::Csv => print!("{}", empty_csv(&args, &opts.csv)?),
+            _ => opts.print_empty_message(),
         }
-        return Ok(());
+        return Ok(skip_filtered(ProcessResults::new(0), filtered));
     }
     if verbose {
         ui::print_info(&format!("Found {} matching pages.", pages.len()));
     }
+    if args.ids_only {
+        let ids: Vec<&str> = pages.iter().filter_map(|p| p.page_id()).collect();
+        let output_content = format_ids(&ids, &format, &opts.csv)?;
+        match &args.output_file {
+            Some(path) => std::fs::write(path, output_content)?,
+            None => println!("{}", output_content),
+        }
+        let mut results = ProcessResults::new(pages.len());
+        results.processed = ids.len();
+        results.success = ids.len();
+        results.skipped = pages.len() - ids.len();
+        return Ok(skip_filtered(results, filtered));
+    }
     // Collect page data with tags - use parallel processing for large sets
     if verbose {
         ui::print_step("Retrieving tags for pages...");
     }
-    let progress = if show_progress && !is_structured {
+    let progress = if opts.show_progress && !is_structured {
         Some(ui::create_progress_bar(pages.len() as u64))
     } else {
         None
     };
 
-    // Use rayon for parallel tag fetching on large datasets
-    use rayon::prelude::*;
-    use std::sync::atomic::{AtomicUsize, Ordering};
+    let max_inflight = args.max_inflight_pages.filter(|&max| pages.len() > max);
+    if let Some(max) = max_inflight {
+        let streamable = format == OutputFormat::Csv && args.show_pages && !args.tags_only;
+        if !streamable {
+            if let Some(p) = &progress {
+                p.finish_and_clear();
+            }
+            anyhow::bail!(
+                "{} pages exceed --max-inflight-pages {}; only CSV page output can be written \
+                 incrementally. Use --format csv, narrow the query, or raise the limit",
+                pages.len(),
+                max
+            );
+        }
+        let started = Instant::now();
+        let results = crate::commands::in_pool(opts.read_concurrency, || {
+            stream_csv(&pages, max, &args, client, progress, verbose, &opts.csv)
+        });
+        opts.timing.record("labels", started);
+        return results.map(|results| skip_filtered(results, filtered));
+    }
+
     let progress_counter = AtomicUsize::new(0);
-    let page_data: Vec<PageData> = pages
+    let started = Instant::now();
+    let mut page_data = crate::commands::in_pool(opts.read_concurrency, || {
+        collect_page_data(
+            &pages,
+            client,
+            progress.as_ref(),
+            &progress_counter,
+            args.detailed,
+            args.with_history,
+        )
+    });
+    let tag_count = TagCountFilter::from_args(&args);
+    page_data.retain(|page| tag_count.keeps(page));
+    order_page_tags(&mut page_data, args.preserve_tag_order);
+    opts.timing.record("labels", started);
+
+    let all_tags = unique_tags(&page_data, aliases.as_ref(), args.preserve_tag_order);
+
+    if let Some(p) = &progress {
+        p.finish_and_clear();
+    }
+
+    if let Some(dir) = &args.output_dir {
+        let manifest = write_output_dir(
+            Path::new(dir),
+            &mut page_data,
+            &format,
+            &args.cql_expression,
+            &opts.csv,
+        )?;
+        if verbose {
+            ui::print_success(&format!(
+                "Wrote {} files and manifest.json to {}",
+                manifest.files.len(),
+                dir
+            ));
+        }
+    } else if let Some(baseline) = &baseline {
+        let diffs = diff_against_baseline(&page_data, baseline);
+        output_tag_diffs(&diffs, &format, args.output_file.as_deref(), &opts.csv)?;
+    } else {
+        // Generate output
+        let output_content = if args.case_report {
+            format_case_report(&case_collisions(&all_tags), &format, &opts.csv)?
+        } else if args.tags_only || !args.show_pages {
+            format_tags_only(&all_tags, &format, &opts.csv)
+        } else if args.by_tag {
+            format_by_tag(&pages_by_tag(&page_data), &format, &opts.csv)
+        } else if args.tree && format == OutputFormat::Json {
+            format_as_json_tree(&page_data)
+        } else if args.collapse_common_tags && args.show_pages && !is_structured {
+            let common = strip_common_tags(&mut page_data);
+            let listing = format_page_data(&page_data, &listing_format, args.show_pages, &opts.csv);
+            if common.is_empty() {
+                listing
+            } else {
+                format!("common: {}\n{}", format_tags_list(&common), listing)
+            }
+        } else {
+            format_page_data(&page_data, &listing_format, args.show_pages, &opts.csv)
+        };
+
+        // Output results
+        if let Some(file_path) = args.output_file {
+            std::fs::write(&file_path, output_content)?;
+            if verbose {
+                ui::print_success(&format!("Results saved to {}", file_path));
+            }
+        } else {
+            println!("{}", output_content);
+        }
+    }
+
+    if verbose {
+        eprintln!();
+        ui::print_info(&format!("Total pages processed: {}", page_data.len()));
+        ui::print_info(&format!("Unique tags found: {}", all_tags.len()));
+    }
+
+    let mut results = ProcessResults::new(pages.len());
+    results.processed = page_data.len();
+    results.success = page_data.len();
+    results.skipped = pages.len() - page_data.len();
+    Ok(skip_filtered(results, filtered))
+}
+
+/// Count pages removed by client-side filters as skipped, as the commands
+/// that change tags do
+fn skip_filtered(mut results: ProcessResults, filtered: usize) -> ProcessResults {
+    results.total += filtered;
+    results.skipped += filtered;
+    results
+}
+
+/// Look up pages by id or URL through the content API, skipping the search
+/// index. Pages that cannot be read are reported and left out.
+fn fetch_pages_by_id(
+    refs: &[String],
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<Vec<SearchResultItem>> {
+    let ids = refs
+        .iter()
+        .map(|r| page_id_from_ref(r))
+        .collect::<Result<Vec<_>>>()?;
+    let fetched: Vec<Result<SearchResultItem>> =
+        crate::commands::in_pool(opts.read_concurrency, || {
+            ids.par_iter().map(|id| client.get_content(id)).collect()
+        });
+    Ok(fetched
+        .into_iter()
+        .filter_map(|page| match page {
+            Ok(page) => Some(page),
+            Err(e) => {
+                ui::defer_warning(format!("{:#}", e));
+                None
+            }
+        })
+        .collect())
+}
+
+/// One page's labels as Confluence returned them, for `--raw-labels`
+#[derive(Serialize)]
+struct RawLabels {
+    page_id: String,
+    labels: Vec<serde_json::Value>,
+}
+
+/// Print the unmapped label JSON of each page, whatever --format is. Pages
+/// whose labels cannot be read are reported and count as failed.
+fn print_raw_labels(
+    refs: &[String],
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let ids = refs
+        .iter()
+        .map(|r| page_id_from_ref(r))
+        .collect::<Result<Vec<_>>>()?;
+    let fetched: Vec<Result<Vec<serde_json::Value>>> =
+        crate::commands::in_pool(opts.read_concurrency, || {
+            ids.par_iter()
+                .map(|id| client.get_page_labels_raw(id))
+                .collect()
+        });
+    let mut results = ProcessResults::new(ids.len());
+    let mut pages = Vec::new();
+    for (page_id, labels) in ids.into_iter().zip(fetched) {
+        results.processed += 1;
+        match labels {
+            Ok(labels) => {
+                results.success += 1;
+                pages.push(RawLabels { page_id, labels });
+            }
+            Err(e) => {
+                results.failed += 1;
+                ui::defer_warning(format!("{:#}", e));
+            }
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&pages)?);
+    Ok(results)
+}
+
+/// Fetch tags for `pages` in parallel and build their output records.
+/// `counter` is shared so progress keeps counting across batches. With
+/// `detailed`, tags carry their non-global prefix.
+pub(crate) fn collect_page_data(
+    pages: &[SearchResultItem],
+    client: &ConfluenceClient,
+    progress: Option<&ProgressBar>,
+    counter: &AtomicUsize,
+    detailed: bool,
+    with_history: bool,
+) -> Vec<PageData> {
+    pages
         .par_iter()
         .filter_map(|page| {
+            let Some(page_id) = page.content.as_ref().and_then(|c| c.id.as_ref()) else {
+                ui::defer_warning(format!(
+                    "Skipping page with no ID: {}",
+                    sanitize_text(page.title.as_deref().unwrap_or("Unknown"))
+                ));
+                return None;
+            };
             let content = page.content.as_ref()?;
-            let page_id = content.id.as_ref()?;
             let title = sanitize_text(page.title.as_deref().unwrap_or("Unknown"));
             let space = page.space_name().to_string();
-            let tags = client.get_page_tags(page_id).unwrap_or_default();
+            let space_key = page.space_key().map(str::to_string);
+            // The search usually carries the labels already; fetch them only
+            // when it didn't or cut them short
+            let tags = match content.expanded_labels() {
+                Some(labels) if detailed => Ok(labels.prefixed_names()),
+                Some(labels) => Ok(labels.names()),
+                None if detailed => client.get_page_tags_prefixed(page_id),
+                None => client.get_page_tags(page_id),
+            }
+            .unwrap_or_else(|e| {
+                ui::defer_warning(format!(
+                    "Could not read labels of page {}; listed without tags: {:#}",
+                    page_id, e
+                ));
+                Vec::new()
+            });
             // Extract ancestor titles (they come in order from root to immediate parent)
             let ancestors: Vec<String> = content
                 .ancestors
@@ -127,81 +667,408 @@ pub fn run(
                 .map(|t| sanitize_text(&t))
                 .collect();
 
-            let url = format!(
-                "{}/wiki/pages/viewpage.action?pageId={}",
-                client.base_url().trim_end_matches('/'),
-                page_id
-            );
+            let url = page.web_url(&client.web_base_url()).unwrap_or_default();
+            let content_type = Some(page.content_type())
+                .filter(|t| *t != "page")
+                .map(str::to_string);
 
             // Update progress
-            let count = progress_counter.fetch_add(1, Ordering::Relaxed);
-            if let Some(ref p) = progress {
+            let count = counter.fetch_add(1, Ordering::Relaxed);
+            if let Some(p) = progress {
                 p.set_position((count + 1) as u64);
             }
             Some(PageData {
                 id: page_id.clone(),
                 title,
                 space,
+                space_key,
                 tags,
                 ancestors,
                 url,
+                content_type,
+                history: with_history.then(|| PageHistory::from(content)),
             })
         })
-        .collect();
+        .collect()
+}
 
+/// Write CSV page rows `batch_size` pages at a time so memory stays bounded.
+fn stream_csv(
+    pages: &[SearchResultItem],
+    batch_size: usize,
+    args: &GetArgs,
+    client: &ConfluenceClient,
+    progress: Option<ProgressBar>,
+    verbose: bool,
+    csv_options: &ui::CsvOptions,
+) -> Result<ProcessResults> {
+    let output_file = args.output_file.as_deref();
+    let tag_count = TagCountFilter::from_args(args);
+    let writer: Box<dyn std::io::Write> = match output_file {
+        Some(path) => Box::new(std::fs::File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut wtr = csv_options.writer(writer)?;
+    let counter = AtomicUsize::new(0);
+    let mut written = 0;
     let mut all_tags = HashSet::new();
-    for pd in &page_data {
-        all_tags.extend(pd.tags.iter().cloned());
+
+    for batch in pages.chunks(batch_size.max(1)) {
+        let mut page_data = collect_page_data(
+            batch,
+            client,
+            progress.as_ref(),
+            &counter,
+            args.detailed,
+            args.with_history,
+        );
+        page_data.retain(|page| tag_count.keeps(page));
+        order_page_tags(&mut page_data, args.preserve_tag_order);
+        for page in &page_data {
+            all_tags.extend(page.tags.iter().cloned());
+            wtr.serialize(PageDataCsv::from(page))?;
+        }
+        wtr.flush()?;
+        written += page_data.len();
     }
 
     if let Some(p) = &progress {
         p.finish_and_clear();
     }
+    if verbose {
+        if let Some(path) = output_file {
+            ui::print_success(&format!("Results saved to {}", path));
+        }
+        ui::print_info(&format!("Total pages processed: {}", written));
+        ui::print_info(&format!("Unique tags found: {}", all_tags.len()));
+    }
 
-    // Generate output
-    let output_content = if args.tags_only {
-        format_tags_only(&all_tags, &format)
+    let mut results = ProcessResults::new(pages.len());
+    results.processed = written;
+    results.success = written;
+    results.skipped = pages.len() - written;
+    Ok(results)
+}
+
+/// Index of the files written by `--output-dir`
+#[derive(Serialize)]
+struct Manifest {
+    cql: String,
+    /// RFC 3339 time the export finished
+    generated_at: String,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize)]
+struct ManifestFile {
+    file: String,
+    space: String,
+    pages: usize,
+    /// Distinct tags across the file's pages
+    tags: usize,
+}
+
+/// File name for a space's export: unsafe characters become `_`, and a
+/// numeric suffix keeps names unique
+fn space_file_name(space: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let stem: String = space
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = if stem.is_empty() {
+        "space".to_string()
     } else {
-        format_page_data(&page_data, &format, args.show_pages, client.base_url())
+        stem
     };
+    let mut name = format!("{}.{}", stem, extension);
+    let mut n = 2;
+    while !used.insert(name.clone()) {
+        name = format!("{}-{}.{}", stem, n, extension);
+        n += 1;
+    }
+    name
+}
 
-    // Output results
-    if let Some(file_path) = args.output_file {
-        std::fs::write(&file_path, output_content)?;
-        if verbose {
-            ui::print_success(&format!("Results saved to {}", file_path));
-        }
+/// Write each space's pages to its own file in `dir`, then `manifest.json`
+fn write_output_dir(
+    dir: &Path,
+    page_data: &mut [PageData],
+    format: &OutputFormat,
+    cql: &str,
+    csv_options: &ui::CsvOptions,
+) -> Result<Manifest> {
+    std::fs::create_dir_all(dir).context(format!(
+        "Failed to create output directory: {}",
+        dir.display()
+    ))?;
+    let extension = if *format == OutputFormat::Csv {
+        "csv"
     } else {
-        println!("{}", output_content);
+        "json"
+    };
+    page_data.sort_by(|a, b| a.space.cmp(&b.space));
+
+    let mut used = HashSet::from(["manifest.json".to_string()]);
+    let mut files = Vec::new();
+    for pages in page_data.chunk_by(|a, b| a.space == b.space) {
+        let space = &pages[0].space;
+        let file = space_file_name(space, extension, &mut used);
+        let content = format_page_data(pages, format, true, csv_options);
+        std::fs::write(dir.join(&file), content)?;
+        let tags: HashSet<&String> = pages.iter().flat_map(|p| &p.tags).collect();
+        files.push(ManifestFile {
+            file,
+            space: space.clone(),
+            pages: pages.len(),
+            tags: tags.len(),
+        });
     }
 
-    if verbose {
-        eprintln!();
-        ui::print_info(&format!("Total pages processed: {}", page_data.len()));
-        ui::print_info(&format!("Unique tags found: {}", all_tags.len()));
+    let manifest = Manifest {
+        cql: cql.to_string(),
+        generated_at: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+        files,
+    };
+    std::fs::write(
+        dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(manifest)
+}
+
+/// Remove the tags every page has from each page and return them, in the
+/// first page's order. Nothing is collapsed for fewer than two pages.
+fn strip_common_tags(page_data: &mut [PageData]) -> Vec<String> {
+    let Some((first, rest)) = page_data.split_first() else {
+        return Vec::new();
+    };
+    if rest.is_empty() {
+        return Vec::new();
     }
+    let common: Vec<String> = first
+        .tags
+        .iter()
+        .filter(|tag| rest.iter().all(|page| page.tags.contains(tag)))
+        .cloned()
+        .collect();
+    for page in page_data.iter_mut() {
+        page.tags.retain(|tag| !common.contains(tag));
+    }
+    common
+}
+
+/// Tags per page id from an earlier `get --format json` export
+fn load_baseline(path: &str) -> Result<HashMap<String, Vec<String>>> {
+    #[derive(Deserialize)]
+    struct BaselinePage {
+        id: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+    let content =
+        std::fs::read_to_string(path).context(format!("Failed to read baseline file: {}", path))?;
+    let pages: Vec<BaselinePage> = serde_json::from_str(&content).context(format!(
+        "Failed to parse baseline file {} (expected `get --format json` output)",
+        path
+    ))?;
+    Ok(pages.into_iter().map(|p| (p.id, p.tags)).collect())
+}
+
+/// Tag changes on one page relative to the baseline
+#[derive(Debug, PartialEq, Serialize)]
+struct TagDiff {
+    id: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+    #[serde(skip)]
+    path: String,
+}
+
+/// Pages whose tags differ from the baseline, in result order. Pages missing
+/// from the baseline count as new, so all their tags are reported as added.
+fn diff_against_baseline(
+    page_data: &[PageData],
+    baseline: &HashMap<String, Vec<String>>,
+) -> Vec<TagDiff> {
+    page_data
+        .iter()
+        .filter_map(|page| {
+            let before = baseline
+                .get(&page.id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let added: Vec<String> = page
+                .tags
+                .iter()
+                .filter(|t| !before.contains(t))
+                .cloned()
+                .collect();
+            let removed: Vec<String> = before
+                .iter()
+                .filter(|t| !page.tags.contains(t))
+                .cloned()
+                .collect();
+            if added.is_empty() && removed.is_empty() {
+                return None;
+            }
+            Some(TagDiff {
+                id: page.id.clone(),
+                added,
+                removed,
+                path: build_page_path(&page.space, &page.ancestors, &page.title),
+            })
+        })
+        .collect()
+}
 
+fn output_tag_diffs(
+    diffs: &[TagDiff],
+    format: &OutputFormat,
+    output_file: Option<&str>,
+    csv_options: &ui::CsvOptions,
+) -> Result<()> {
+    let content = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(diffs)?,
+        OutputFormat::Csv => {
+            #[derive(Serialize)]
+            struct TagDiffCsv<'a> {
+                id: &'a str,
+                added: String,
+                removed: String,
+            }
+            let mut wtr = csv_options.writer(vec![])?;
+            for diff in diffs {
+                wtr.serialize(TagDiffCsv {
+                    id: &diff.id,
+                    added: diff.added.join(", "),
+                    removed: diff.removed.join(", "),
+                })?;
+            }
+            String::from_utf8(wtr.into_inner()?)?
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            if diffs.is_empty() {
+                ui::print_info("No tag changes since the baseline.");
+            }
+            for diff in diffs {
+                ui::print_tag_diff(&diff.path, &diff.added, &diff.removed);
+            }
+            return Ok(());
+        }
+    };
+    match output_file {
+        Some(path) => std::fs::write(path, content)?,
+        None => println!("{}", content),
+    }
     Ok(())
 }
 
-fn format_tags_only(tags: &HashSet<String>, format: &OutputFormat) -> String {
-    let mut sorted_tags: Vec<_> = tags.iter().collect();
-    sorted_tags.sort();
+/// Header-only CSV with the columns a non-empty run would have written
+fn empty_csv(args: &GetArgs, csv_options: &ui::CsvOptions) -> Result<String> {
+    let header: &[&str] = if args.ids_only {
+        &["id"]
+    } else if args.baseline.is_some() {
+        &["id", "added", "removed"]
+    } else if args.case_report {
+        &["normalized", "variants"]
+    } else if args.tags_only || !args.show_pages {
+        &["tag"]
+    } else if args.with_history {
+        &[
+            "id",
+            "path",
+            "space",
+            "space_key",
+            "tags",
+            "url",
+            "created_date",
+            "last_modified",
+            "version_number",
+        ]
+    } else {
+        &["id", "path", "space", "space_key", "tags", "url"]
+    };
+    let mut wtr = csv_options.writer(vec![])?;
+    wtr.write_record(header)?;
+    Ok(String::from_utf8(wtr.into_inner()?)?)
+}
+
+/// One id per line, a JSON array, or a single-column CSV
+fn format_ids(ids: &[&str], format: &OutputFormat, csv_options: &ui::CsvOptions) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Json => serde_json::to_string_pretty(ids)?,
+        OutputFormat::Csv => {
+            #[derive(Serialize)]
+            struct IdCsv<'a> {
+                id: &'a str,
+            }
+            let mut wtr = csv_options.writer(vec![])?;
+            for id in ids {
+                wtr.serialize(IdCsv { id })?;
+            }
+            String::from_utf8(wtr.into_inner()?)?.trim_end().to_string()
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => ids.join("\n"),
+    })
+}
+
+/// Sort each page's labels, unless `preserve_order` keeps them as returned
+fn order_page_tags(page_data: &mut [PageData], preserve_order: bool) {
+    if !preserve_order {
+        for page in page_data {
+            page.tags.sort();
+        }
+    }
+}
+
+/// Distinct tags across `page_data`, aliases folded into their canonical
+/// name. Sorted, or with `preserve_order` in order of first appearance.
+fn unique_tags(
+    page_data: &[PageData],
+    aliases: Option<&TagAliases>,
+    preserve_order: bool,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut tags: Vec<String> = page_data
+        .iter()
+        .flat_map(|page| &page.tags)
+        .map(|tag| aliases.map_or(tag.as_str(), |a| a.canonical(tag)))
+        .filter(|tag| seen.insert(*tag))
+        .map(str::to_string)
+        .collect();
+    if !preserve_order {
+        tags.sort();
+    }
+    tags
+}
+
+/// List `tags` in the order given
+fn format_tags_only(
+    tags: &[String],
+    format: &OutputFormat,
+    csv_options: &ui::CsvOptions,
+) -> String {
     match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&sorted_tags).unwrap_or_default(),
+        OutputFormat::Json => serde_json::to_string_pretty(tags).unwrap_or_default(),
         OutputFormat::Csv => {
-            let mut wtr = csv::Writer::from_writer(vec![]);
+            let mut wtr = csv_options.writer(vec![]).unwrap();
             #[derive(Serialize)]
             struct TagCsv<'a> {
                 tag: &'a str,
             }
-            for tag in sorted_tags {
+            for tag in tags {
                 wtr.serialize(TagCsv { tag }).unwrap();
             }
             String::from_utf8(wtr.into_inner().unwrap()).unwrap()
         }
         OutputFormat::Simple | OutputFormat::Verbose => {
-            if sorted_tags.is_empty() {
+            if tags.is_empty() {
                 return "No tags found.".to_string();
             }
             let mut table = Table::new();
@@ -212,14 +1079,113 @@ fn format_tags_only(tags: &HashSet<String>, format: &OutputFormat) -> String {
                     .add_attribute(Attribute::Bold)
                     .fg(Color::Cyan)]);
 
-            for tag in sorted_tags {
-                table.add_row(vec![tag]);
+            for tag in tags {
+                table.add_row(vec![tag_cell(tag)]);
             }
             table.to_string()
         }
     }
 }
 
+/// Invert pages→tags into tags→pages, pages in listing order
+fn pages_by_tag(page_data: &[PageData]) -> BTreeMap<&str, Vec<&PageData>> {
+    let mut by_tag: BTreeMap<&str, Vec<&PageData>> = BTreeMap::new();
+    for page in page_data {
+        for tag in &page.tags {
+            by_tag.entry(tag.as_str()).or_default().push(page);
+        }
+    }
+    by_tag
+}
+
+/// A page as listed under a tag by `--by-tag`
+#[derive(Serialize)]
+struct TaggedPage<'a> {
+    id: &'a str,
+    title: &'a str,
+    space: &'a str,
+    url: &'a str,
+}
+
+impl<'a> From<&'a PageData> for TaggedPage<'a> {
+    fn from(page: &'a PageData) -> Self {
+        Self {
+            id: &page.id,
+            title: &page.title,
+            space: &page.space,
+            url: &page.url,
+        }
+    }
+}
+
+fn format_by_tag(
+    by_tag: &BTreeMap<&str, Vec<&PageData>>,
+    format: &OutputFormat,
+    csv_options: &ui::CsvOptions,
+) -> String {
+    match format {
+        OutputFormat::Json => {
+            let json: BTreeMap<&str, Vec<TaggedPage>> = by_tag
+                .iter()
+                .map(|(tag, pages)| (*tag, pages.iter().map(|p| TaggedPage::from(*p)).collect()))
+                .collect();
+            serde_json::to_string_pretty(&json).unwrap_or_default()
+        }
+        OutputFormat::Csv => {
+            // The csv crate can't serialize flattened structs, so rows are
+            // written field by field
+            let mut wtr = csv_options.writer(vec![]).unwrap();
+            wtr.write_record(["tag", "id", "title", "space", "url"])
+                .unwrap();
+            for (tag, pages) in by_tag {
+                for page in pages {
+                    wtr.write_record([*tag, &page.id, &page.title, &page.space, &page.url])
+                        .unwrap();
+                }
+            }
+            String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            if by_tag.is_empty() {
+                return "No tags found.".to_string();
+            }
+            let mut lines = Vec::new();
+            for (tag, pages) in by_tag {
+                lines.push(format!("{} ({})", tag.bold().green(), pages.len()));
+                let mut paths: Vec<(String, &str)> = pages
+                    .iter()
+                    .map(|p| {
+                        (
+                            build_page_path(&p.space, &p.ancestors, &p.title),
+                            p.url.as_str(),
+                        )
+                    })
+                    .collect();
+                paths.sort();
+                for (i, (path, url)) in paths.iter().enumerate() {
+                    let connector = if i + 1 == paths.len() {
+                        "└──"
+                    } else {
+                        "├──"
+                    };
+                    lines.push(format!("{} {}", connector, make_clickable(path, url)));
+                }
+            }
+            lines.join("\n")
+        }
+    }
+}
+
+/// Format to list pages in. Simple and verbose listings differ only in tree
+/// versus paths, so `--tree` and `--flat` pick the other text format.
+fn listing_format(format: OutputFormat, tree: bool, flat: bool) -> OutputFormat {
+    match format {
+        OutputFormat::Simple if tree => OutputFormat::Verbose,
+        OutputFormat::Verbose if flat => OutputFormat::Simple,
+        format => format,
+    }
+}
+
 /// Format page data as either a tree view (verbose) or path format (simple).
 /// - Verbose: Shows hierarchical tree structure with ├── └── connectors
 /// - Simple: Shows path format like /Space/Parent/Page [tag1, tag2]
@@ -227,7 +1193,7 @@ fn format_page_data(
     page_data: &[PageData],
     format: &OutputFormat,
     show_pages: bool,
-    base_url: &str,
+    csv_options: &ui::CsvOptions,
 ) -> String {
     match format {
         OutputFormat::Json => {
@@ -244,27 +1210,10 @@ fn format_page_data(
             }
         }
         OutputFormat::Csv => {
-            let mut wtr = csv::Writer::from_writer(vec![]);
+            let mut wtr = csv_options.writer(vec![]).unwrap();
             if show_pages {
-                #[derive(Serialize)]
-                struct PageDataCsv<'a> {
-                    id: &'a str,
-                    path: String,
-                    space: &'a str,
-                    tags: String,
-                    url: &'a str,
-                }
-
                 for page in page_data {
-                    let path = build_page_path(&page.space, &page.ancestors, &page.title);
-                    wtr.serialize(PageDataCsv {
-                        id: &page.id,
-                        path,
-                        space: &page.space,
-                        tags: page.tags.join(", "),
-                        url: &page.url,
-                    })
-                    .unwrap();
+                    wtr.serialize(PageDataCsv::from(page)).unwrap();
                 }
             } else {
                 let mut all_tags: HashSet<String> = HashSet::new();
@@ -290,7 +1239,7 @@ fn format_page_data(
                 return "No pages found.".to_string();
             }
             if show_pages {
-                format_as_paths(page_data, base_url)
+                format_as_paths(page_data)
             } else {
                 format_tags_as_table(page_data)
             }
@@ -300,7 +1249,7 @@ fn format_page_data(
                 return "No pages found.".to_string();
             }
             if show_pages {
-                format_as_tree(page_data, base_url)
+                format_as_tree(page_data)
             } else {
                 format_tags_as_table(page_data)
             }
@@ -310,11 +1259,11 @@ fn format_page_data(
 
 // Use shared functions from ui module
 use crate::ui::{
-    build_page_path, format_directory, format_space, format_tags_list, make_page_clickable,
+    build_page_path, format_directory, format_space, format_tags_list, make_clickable,
 };
 
 /// Format pages as simple path format: /Space/Parent/Page [tag1, tag2]
-fn format_as_paths(page_data: &[PageData], base_url: &str) -> String {
+fn format_as_paths(page_data: &[PageData]) -> String {
     let mut lines: Vec<String> = Vec::new();
 
     // Sort pages by their full path for consistent output
@@ -327,26 +1276,28 @@ fn format_as_paths(page_data: &[PageData], base_url: &str) -> String {
     for page in sorted_pages {
         let path = build_page_path(&page.space, &page.ancestors, &page.title);
         let tags = format_tags_list(&page.tags);
-        let clickable_path = make_page_clickable(&path, &page.id, base_url);
-        lines.push(format!("{} {}", clickable_path, tags));
+        let clickable_path = make_clickable(&path, &page.url);
+        let history = page.history.as_ref().map(PageHistory::note);
+        lines.push(format!(
+            "{} {}{}",
+            clickable_path,
+            tags,
+            history.unwrap_or_default().dimmed()
+        ));
     }
     lines.join("\n")
 }
 
-/// Format pages as a tree structure similar to the `tree` command
-fn format_as_tree(page_data: &[PageData], base_url: &str) -> String {
-    use std::collections::BTreeMap;
-
-    // Build a tree structure: Map<space, Map<path_component, children>>
-    // We'll use a simple approach: collect all paths and render them as a tree
-
-    #[derive(Default)]
-    struct TreeNode {
-        children: BTreeMap<String, TreeNode>,
-        // If this node is a page (leaf), store page info
-        page_info: Option<(String, String, Vec<String>)>, // (id, title, tags)
-    }
+#[derive(Default)]
+struct TreeNode<'a> {
+    children: BTreeMap<String, TreeNode<'a>>,
+    // If this node is a page (leaf), store page info
+    page: Option<&'a PageData>,
+}
 
+/// Group pages by space and ancestor titles: Map<space, node>, where each
+/// node's children are keyed by path component (ancestor or page title)
+fn build_tree(page_data: &[PageData]) -> BTreeMap<String, TreeNode<'_>> {
     let mut root: BTreeMap<String, TreeNode> = BTreeMap::new();
 
     // Insert all pages into the tree
@@ -361,15 +1312,52 @@ fn format_as_tree(page_data: &[PageData], base_url: &str) -> String {
 
         // Insert the page itself
         let page_node = current.children.entry(page.title.clone()).or_default();
-        page_node.page_info = Some((page.id.clone(), page.title.clone(), page.tags.clone()));
+        page_node.page = Some(page);
+    }
+    root
+}
+
+/// JSON form of the page tree: pages directly under a node, and nested
+/// containers (spaces or ancestor pages) by title
+#[derive(Serialize)]
+struct JsonTreeNode<'a> {
+    pages: Vec<&'a PageData>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    children: BTreeMap<String, JsonTreeNode<'a>>,
+}
+
+impl<'a> From<&TreeNode<'a>> for JsonTreeNode<'a> {
+    fn from(node: &TreeNode<'a>) -> Self {
+        let mut pages = Vec::new();
+        let mut children = BTreeMap::new();
+        for (name, child) in &node.children {
+            if let Some(page) = child.page {
+                pages.push(page);
+            }
+            if !child.children.is_empty() {
+                children.insert(name.clone(), JsonTreeNode::from(child));
+            }
+        }
+        Self { pages, children }
     }
+}
+
+/// Format pages as nested JSON `{space: {pages: [...], children: {...}}}`,
+/// grouped the same way as the text tree
+fn format_as_json_tree(page_data: &[PageData]) -> String {
+    let root = build_tree(page_data);
+    let tree: BTreeMap<&String, JsonTreeNode> = root
+        .iter()
+        .map(|(space, node)| (space, JsonTreeNode::from(node)))
+        .collect();
+    serde_json::to_string_pretty(&tree).unwrap_or_default()
+}
 
-    fn render_tree(
-        node: &BTreeMap<String, TreeNode>,
-        prefix: &str,
-        base_url: &str,
-        is_root: bool,
-    ) -> Vec<String> {
+/// Format pages as a tree structure similar to the `tree` command
+fn format_as_tree(page_data: &[PageData]) -> String {
+    let root = build_tree(page_data);
+
+    fn render_tree(node: &BTreeMap<String, TreeNode>, prefix: &str, is_root: bool) -> Vec<String> {
         let mut lines = Vec::new();
         let entries: Vec<_> = node.iter().collect();
         let count = entries.len();
@@ -392,11 +1380,19 @@ fn format_as_tree(page_data: &[PageData], base_url: &str) -> String {
             };
 
             // Format this node
-            let display_name = if let Some((ref id, ref _title, ref tags)) = child.page_info {
+            let display_name = if let Some(page) = child.page {
                 // This is a page - make it clickable and show tags
-                let tags_str = format_tags_list(tags);
-                let clickable = make_page_clickable(name, id, base_url);
-                format!("{}{}{} {}", prefix, connector, clickable, tags_str)
+                let tags_str = format_tags_list(&page.tags);
+                let clickable = make_clickable(name, &page.url);
+                let history = page.history.as_ref().map(PageHistory::note);
+                format!(
+                    "{}{}{} {}{}",
+                    prefix,
+                    connector,
+                    clickable,
+                    tags_str,
+                    history.unwrap_or_default().dimmed()
+                )
             } else {
                 // This is just a container (space or parent page not in results)
                 format!("{}{}{}", prefix, connector, format_directory(name))
@@ -406,7 +1402,7 @@ fn format_as_tree(page_data: &[PageData], base_url: &str) -> String {
 
             // Recurse into children
             if !child.children.is_empty() {
-                lines.extend(render_tree(&child.children, &child_prefix, base_url, false));
+                lines.extend(render_tree(&child.children, &child_prefix, false));
             }
         }
 
@@ -425,7 +1421,7 @@ fn format_as_tree(page_data: &[PageData], base_url: &str) -> String {
         // Render children of this space
         let is_last_space = i == space_count - 1;
         let _ = is_last_space; // We don't need different prefix for last space
-        all_lines.extend(render_tree(&space_node.children, "", base_url, false));
+        all_lines.extend(render_tree(&space_node.children, "", false));
 
         // Add blank line between spaces (except after last)
         if i < space_count - 1 {
@@ -436,6 +1432,56 @@ fn format_as_tree(page_data: &[PageData], base_url: &str) -> String {
     all_lines.join("\n")
 }
 
+/// Tags that differ only by case or surrounding whitespace, keyed by their
+/// normalized form. Tags without a collision are left out.
+fn case_collisions<'a>(
+    tags: impl IntoIterator<Item = &'a String>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for tag in tags {
+        groups
+            .entry(tag.trim().to_lowercase())
+            .or_default()
+            .push(tag.clone());
+    }
+    groups.retain(|_, variants| variants.len() > 1);
+    for variants in groups.values_mut() {
+        variants.sort();
+    }
+    groups
+}
+
+fn format_case_report(
+    groups: &BTreeMap<String, Vec<String>>,
+    format: &OutputFormat,
+    csv_options: &ui::CsvOptions,
+) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Json => serde_json::to_string_pretty(groups)?,
+        OutputFormat::Csv => {
+            let mut wtr = csv_options.writer(vec![])?;
+            wtr.write_record(["normalized", "variants"])?;
+            for (normalized, variants) in groups {
+                wtr.write_record([normalized.as_str(), &variants.join(", ")])?;
+            }
+            String::from_utf8(wtr.into_inner()?)?
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            if groups.is_empty() {
+                return Ok("No labels differ only by case or whitespace.".to_string());
+            }
+            groups
+                .iter()
+                .map(|(normalized, variants)| {
+                    let quoted: Vec<String> = variants.iter().map(|v| format!("{:?}", v)).collect();
+                    format!("{}: {}", normalized, quoted.join(", "))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    })
+}
+
 /// Format tags only as a table (when show_pages is false)
 fn format_tags_as_table(page_data: &[PageData]) -> String {
     let mut all_tags: HashSet<String> = HashSet::new();
@@ -457,32 +1503,236 @@ fn format_tags_as_table(page_data: &[PageData]) -> String {
         let mut sorted: Vec<_> = all_tags.into_iter().collect();
         sorted.sort();
         for tag in sorted {
-            table.add_row(vec![tag]);
+            table.add_row(vec![tag_cell(&tag)]);
         }
         table.to_string()
     }
 }
 
+/// Table cell for a tag, colored by its prefix: team tags blue, personal
+/// tags magenta, system tags yellow, global tags plain
+fn tag_cell(tag: &str) -> Cell {
+    let cell = Cell::new(tag);
+    match tag.split_once(':').map(|(prefix, _)| prefix) {
+        Some("team") => cell.fg(Color::Blue),
+        Some("my") => cell.fg(Color::Magenta),
+        Some("system") => cell.fg(Color::Yellow),
+        _ => cell,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::collections::HashSet;
 
+    fn page_with_tags(id: &str, tags: &[&str]) -> PageData {
+        PageData {
+            id: id.to_string(),
+            title: format!("Page {}", id),
+            space: "Docs".to_string(),
+            space_key: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ancestors: Vec::new(),
+            url: String::new(),
+            content_type: None,
+            history: None,
+        }
+    }
+
+    #[test]
+    fn filtered_pages_count_as_skipped() {
+        // Every page filtered out is still a match, not an empty result
+        let results = skip_filtered(ProcessResults::new(0), 3);
+        assert_eq!(results.total, 3);
+        assert_eq!(results.skipped, 3);
+        assert_eq!(results.success, 0);
+    }
+
+    #[test]
+    fn by_tag_lists_pages_under_each_tag() {
+        let pages = vec![
+            page_with_tags("1", &["deprecated", "docs"]),
+            page_with_tags("2", &["deprecated"]),
+            page_with_tags("3", &[]),
+        ];
+        let by_tag = pages_by_tag(&pages);
+        assert_eq!(
+            by_tag.keys().copied().collect::<Vec<_>>(),
+            ["deprecated", "docs"]
+        );
+        assert_eq!(by_tag["deprecated"].len(), 2);
+
+        let json: serde_json::Value = serde_json::from_str(&format_by_tag(
+            &by_tag,
+            &OutputFormat::Json,
+            &ui::CsvOptions::default(),
+        ))
+        .unwrap();
+        assert_eq!(json["deprecated"][1]["id"], "2");
+        assert_eq!(json["docs"][0]["title"], "Page 1");
+
+        let csv = format_by_tag(&by_tag, &OutputFormat::Csv, &ui::CsvOptions::default());
+        assert_eq!(csv.lines().next(), Some("tag,id,title,space,url"));
+        assert_eq!(csv.lines().count(), 4);
+
+        let text = format_by_tag(&by_tag, &OutputFormat::Simple, &ui::CsvOptions::default());
+        assert!(text.contains("/Docs/Page 2"));
+    }
+
+    #[test]
+    fn tag_count_filter_keeps_tagged_or_untagged_pages() {
+        let bare = page_with_tags("1", &[]);
+        let tagged = page_with_tags("2", &["a"]);
+        let untagged = TagCountFilter {
+            min: 0,
+            max: Some(0),
+        };
+        assert!(untagged.keeps(&bare) && !untagged.keeps(&tagged));
+        let any = TagCountFilter { min: 1, max: None };
+        assert!(!any.keeps(&bare) && any.keeps(&tagged));
+        assert!(TagCountFilter::default().keeps(&bare));
+    }
+
+    #[test]
+    fn write_output_dir_splits_by_space_with_manifest() {
+        let dir = std::env::temp_dir().join(format!("ctag-export-{}", std::process::id()));
+        let mut pages = vec![
+            page_with_tags("1", &["a", "b"]),
+            page_with_tags("2", &["b"]),
+            page_with_tags("3", &["c"]),
+        ];
+        pages[1].space = "Eng / Ops".to_string();
+
+        let manifest = write_output_dir(
+            &dir,
+            &mut pages,
+            &OutputFormat::Json,
+            "type = page",
+            &ui::CsvOptions::default(),
+        )
+        .unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("manifest.json")).unwrap())
+                .unwrap();
+        let docs: Vec<serde_json::Value> =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("Docs.json")).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(written["cql"], "type = page");
+        assert_eq!(written["files"][0]["file"], "Docs.json");
+        assert_eq!(written["files"][0]["pages"], 2);
+        assert_eq!(written["files"][0]["tags"], 3);
+        assert_eq!(written["files"][1]["file"], "Eng___Ops.json");
+        assert_eq!(docs.len(), 2);
+    }
+
+    #[test]
+    fn format_ids_per_format() {
+        let ids = ["1", "22"];
+        let csv = ui::CsvOptions::default();
+        assert_eq!(
+            format_ids(&ids, &OutputFormat::Simple, &csv).unwrap(),
+            "1\n22"
+        );
+        assert_eq!(
+            format_ids(&ids, &OutputFormat::Csv, &csv).unwrap(),
+            "id\n1\n22"
+        );
+        let json: Vec<String> =
+            serde_json::from_str(&format_ids(&ids, &OutputFormat::Json, &csv).unwrap()).unwrap();
+        assert_eq!(json, vec!["1", "22"]);
+    }
+
+    #[test]
+    fn strip_common_tags_leaves_per_page_deltas() {
+        let mut pages = vec![
+            page_with_tags("1", &["b", "a", "x"]),
+            page_with_tags("2", &["a", "b"]),
+            page_with_tags("3", &["y", "a", "b"]),
+        ];
+        assert_eq!(strip_common_tags(&mut pages), vec!["b", "a"]);
+        assert_eq!(pages[0].tags, vec!["x"]);
+        assert!(pages[1].tags.is_empty());
+        assert_eq!(pages[2].tags, vec!["y"]);
+
+        let mut single = vec![page_with_tags("1", &["a"])];
+        assert!(strip_common_tags(&mut single).is_empty());
+        assert_eq!(single[0].tags, vec!["a"]);
+    }
+
+    #[test]
+    fn diff_against_baseline_reports_only_changed_pages() {
+        let baseline = HashMap::from([
+            ("1".to_string(), vec!["a".to_string(), "b".to_string()]),
+            ("2".to_string(), vec!["c".to_string()]),
+        ]);
+        let pages = vec![
+            page_with_tags("1", &["b", "d"]),
+            page_with_tags("2", &["c"]),
+            page_with_tags("3", &["e"]),
+        ];
+        let diffs = diff_against_baseline(&pages, &baseline);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].id, "1");
+        assert_eq!(diffs[0].added, vec!["d"]);
+        assert_eq!(diffs[0].removed, vec!["a"]);
+        assert_eq!(diffs[1].id, "3");
+        assert_eq!(diffs[1].added, vec!["e"]);
+
+        let json = serde_json::to_value(&diffs[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"id": "1", "added": ["d"], "removed": ["a"]})
+        );
+    }
+
+    #[test]
+    fn case_collisions_keep_only_groups_with_variants() {
+        let tags: HashSet<String> = ["Draft", "draft", "draft ", "final"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let groups = case_collisions(&tags);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups["draft"], vec!["Draft", "draft", "draft "]);
+    }
+
     #[test]
     fn format_tags_only_table_empty() {
-        let tags: HashSet<String> = HashSet::new();
-        let out = format_tags_only(&tags, &OutputFormat::Simple);
+        let out = format_tags_only(&[], &OutputFormat::Simple, &ui::CsvOptions::default());
         assert_eq!(out.trim(), "No tags found.");
     }
 
     #[test]
     fn format_tags_only_json_sorted() {
-        let mut tags: HashSet<String> = HashSet::new();
-        tags.insert("b".to_string());
-        tags.insert("a".to_string());
-        let out = format_tags_only(&tags, &OutputFormat::Json);
+        let mut pages = vec![page_with_tags("1", &["b", "a"])];
+        let tags = unique_tags(&pages, None, false);
+        let out = format_tags_only(&tags, &OutputFormat::Json, &ui::CsvOptions::default());
         let parsed: Vec<String> = serde_json::from_str(&out).unwrap();
         assert_eq!(parsed, vec!["a".to_string(), "b".to_string()]);
+
+        order_page_tags(&mut pages, false);
+        assert_eq!(pages[0].tags, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn preserve_tag_order_keeps_api_order() {
+        let mut pages = vec![
+            page_with_tags("1", &["zeta", "alpha"]),
+            page_with_tags("2", &["beta", "zeta"]),
+        ];
+        order_page_tags(&mut pages, true);
+        assert_eq!(pages[0].tags, vec!["zeta", "alpha"]);
+        assert_eq!(
+            unique_tags(&pages, None, true),
+            vec!["zeta", "alpha", "beta"]
+        );
+        assert_eq!(
+            unique_tags(&pages, None, false),
+            vec!["alpha", "beta", "zeta"]
+        );
     }
 
     #[test]
@@ -534,37 +1784,67 @@ mod tests {
                 id: "2".to_string(),
                 title: "Zebra".to_string(),
                 space: "DOCS".to_string(),
+                space_key: None,
                 tags: vec!["z-tag".to_string()],
                 ancestors: vec![],
                 url: "http://example.com/2".to_string(),
+                content_type: None,
+                history: None,
             },
             PageData {
                 id: "1".to_string(),
                 title: "Alpha".to_string(),
                 space: "DOCS".to_string(),
+                space_key: None,
                 tags: vec!["a-tag".to_string()],
                 ancestors: vec![],
                 url: "http://example.com/1".to_string(),
+                content_type: None,
+                history: None,
             },
         ];
-        let output = format_as_paths(&pages, "https://example.atlassian.net");
+        let output = format_as_paths(&pages);
         let lines: Vec<&str> = output.lines().collect();
         // Should be sorted alphabetically by path
         assert!(lines[0].contains("Alpha"));
         assert!(lines[1].contains("Zebra"));
     }
 
+    #[test]
+    fn tree_and_flat_choose_the_text_layout() {
+        assert_eq!(
+            listing_format(OutputFormat::Simple, true, false),
+            OutputFormat::Verbose
+        );
+        assert_eq!(
+            listing_format(OutputFormat::Verbose, false, true),
+            OutputFormat::Simple
+        );
+        assert_eq!(
+            listing_format(OutputFormat::Simple, false, false),
+            OutputFormat::Simple
+        );
+        // JSON keeps its own --tree handling
+        assert_eq!(
+            listing_format(OutputFormat::Json, true, false),
+            OutputFormat::Json
+        );
+    }
+
     #[test]
     fn format_as_tree_single_page() {
         let pages = vec![PageData {
             id: "123".to_string(),
             title: "TestPage".to_string(),
             space: "MYSPACE".to_string(),
+            space_key: None,
             tags: vec!["tag1".to_string()],
             ancestors: vec![],
             url: "http://example.com/123".to_string(),
+            content_type: None,
+            history: None,
         }];
-        let output = format_as_tree(&pages, "https://example.atlassian.net");
+        let output = format_as_tree(&pages);
         // Should contain the space name and page
         assert!(output.contains("MYSPACE"));
         assert!(output.contains("TestPage"));
@@ -578,20 +1858,26 @@ mod tests {
                 id: "1".to_string(),
                 title: "ChildPage".to_string(),
                 space: "DOCS".to_string(),
+                space_key: None,
                 tags: vec!["child-tag".to_string()],
                 ancestors: vec!["ParentPage".to_string()],
                 url: "http://example.com/1".to_string(),
+                content_type: None,
+                history: None,
             },
             PageData {
                 id: "2".to_string(),
                 title: "ParentPage".to_string(),
                 space: "DOCS".to_string(),
+                space_key: None,
                 tags: vec!["parent-tag".to_string()],
                 ancestors: vec![],
                 url: "http://example.com/2".to_string(),
+                content_type: None,
+                history: None,
             },
         ];
-        let output = format_as_tree(&pages, "https://example.atlassian.net");
+        let output = format_as_tree(&pages);
         // Should show hierarchy with tree connectors
         assert!(output.contains("DOCS"));
         assert!(output.contains("ParentPage"));
@@ -600,17 +1886,116 @@ mod tests {
         assert!(output.contains("└──") || output.contains("├──"));
     }
 
+    #[test]
+    fn format_as_json_tree_nests_by_space_and_ancestors() {
+        let pages = vec![
+            PageData {
+                id: "1".to_string(),
+                title: "ChildPage".to_string(),
+                space: "DOCS".to_string(),
+                space_key: None,
+                tags: vec!["child-tag".to_string()],
+                ancestors: vec!["ParentPage".to_string()],
+                url: "http://example.com/1".to_string(),
+                content_type: None,
+                history: None,
+            },
+            PageData {
+                id: "2".to_string(),
+                title: "ParentPage".to_string(),
+                space: "DOCS".to_string(),
+                space_key: None,
+                tags: vec![],
+                ancestors: vec![],
+                url: "http://example.com/2".to_string(),
+                content_type: None,
+                history: None,
+            },
+        ];
+        let json: serde_json::Value = serde_json::from_str(&format_as_json_tree(&pages)).unwrap();
+        let docs = &json["DOCS"];
+        assert_eq!(docs["pages"][0]["id"], "2");
+        assert_eq!(docs["children"]["ParentPage"]["pages"][0]["id"], "1");
+        assert!(docs["children"]["ParentPage"].get("children").is_none());
+    }
+
+    #[test]
+    fn with_history_adds_dates_and_version_to_output() {
+        let content: ctag::models::Content = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "version": {"number": 3, "when": "2024-05-01T10:00:00.000Z"},
+            "history": {"createdDate": "2023-01-02T09:00:00.000Z"}
+        }))
+        .unwrap();
+        let mut page = page_with_tags("1", &["x"]);
+        page.history = Some(PageHistory::from(&content));
+
+        let json = serde_json::to_value(&page).unwrap();
+        assert_eq!(json["version_number"], 3);
+        assert_eq!(json["created_date"], "2023-01-02T09:00:00.000Z");
+        assert_eq!(json["last_modified"], "2024-05-01T10:00:00.000Z");
+
+        let csv = format_page_data(
+            &[page],
+            &OutputFormat::Csv,
+            true,
+            &ui::CsvOptions::default(),
+        );
+        let header = csv.lines().next().unwrap();
+        assert!(header.ends_with("url,created_date,last_modified,version_number"));
+        assert!(csv.contains(",3"));
+
+        let plain = serde_json::to_value(page_with_tags("2", &[])).unwrap();
+        assert!(plain.get("version_number").is_none());
+    }
+
+    #[test]
+    fn text_output_keeps_long_titles_and_tags_whole() {
+        let title = "A very long page title ".repeat(10);
+        let tag = "a-very-long-tag-".repeat(12);
+        let pages = vec![PageData {
+            id: "1".to_string(),
+            title: title.clone(),
+            space: "DOCS".to_string(),
+            space_key: None,
+            tags: vec![tag.clone()],
+            ancestors: Vec::new(),
+            url: String::new(),
+            content_type: None,
+            history: None,
+        }];
+        for (format, show_pages) in [
+            (OutputFormat::Simple, true),
+            (OutputFormat::Verbose, true),
+            (OutputFormat::Simple, false),
+        ] {
+            let output = format_page_data(&pages, &format, show_pages, &ui::CsvOptions::default());
+            assert!(output.contains(&tag), "{:?} cut the tag", format);
+            if show_pages {
+                assert!(output.contains(&title), "{:?} cut the title", format);
+            }
+        }
+    }
+
     #[test]
     fn format_page_data_simple_with_ancestors() {
         let pages = vec![PageData {
             id: "123".to_string(),
             title: "DeepPage".to_string(),
             space: "MYSPACE".to_string(),
+            space_key: None,
             tags: vec!["important".to_string()],
             ancestors: vec!["Level1".to_string(), "Level2".to_string()],
             url: "http://example.com/123".to_string(),
+            content_type: None,
+            history: None,
         }];
-        let output = format_page_data(&pages, &OutputFormat::Simple, true, "https://example.com");
+        let output = format_page_data(
+            &pages,
+            &OutputFormat::Simple,
+            true,
+            &ui::CsvOptions::default(),
+        );
         // Simple mode should show path format
         assert!(output.contains("/MYSPACE/Level1/Level2/DeepPage"));
         assert!(output.contains("[important]"));
@@ -622,11 +2007,19 @@ mod tests {
             id: "123".to_string(),
             title: "TestPage".to_string(),
             space: "MYSPACE".to_string(),
+            space_key: None,
             tags: vec!["tag1".to_string()],
             ancestors: vec!["Parent".to_string()],
             url: "http://example.com/123".to_string(),
+            content_type: None,
+            history: None,
         }];
-        let output = format_page_data(&pages, &OutputFormat::Json, true, "https://example.com");
+        let output = format_page_data(
+            &pages,
+            &OutputFormat::Json,
+            true,
+            &ui::CsvOptions::default(),
+        );
         let parsed: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
         assert_eq!(parsed.len(), 1);
         assert_eq!(parsed[0]["ancestors"][0], "Parent");
@@ -638,11 +2031,14 @@ mod tests {
             id: "123".to_string(),
             title: "TestPage".to_string(),
             space: "MYSPACE".to_string(),
+            space_key: None,
             tags: vec!["tag1".to_string()],
             ancestors: vec!["Parent".to_string()],
             url: "http://example.com/123".to_string(),
+            content_type: None,
+            history: None,
         }];
-        let output = format_page_data(&pages, &OutputFormat::Csv, true, "https://example.com");
+        let output = format_page_data(&pages, &OutputFormat::Csv, true, &ui::CsvOptions::default());
         // CSV should have path column
         assert!(output.contains("/MYSPACE/Parent/TestPage"));
     }
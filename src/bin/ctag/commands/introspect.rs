@@ -0,0 +1,201 @@
+use crate::ui;
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use ctag::models::OutputFormat;
+use serde::Serialize;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # Describe every command and flag for a wrapper tool
+  ctag introspect --format json
+")]
+pub struct IntrospectArgs {}
+
+/// The CLI's commands, flags and output formats, taken from the clap model
+#[derive(Debug, Serialize)]
+struct Capabilities {
+    version: Option<String>,
+    formats: Vec<String>,
+    global_flags: Vec<FlagInfo>,
+    commands: Vec<CommandInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommandInfo {
+    name: String,
+    about: Option<String>,
+    arguments: Vec<FlagInfo>,
+    flags: Vec<FlagInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct FlagInfo {
+    /// `--long` form for flags, the argument id for positional arguments
+    name: String,
+    short: Option<char>,
+    help: Option<String>,
+    takes_value: bool,
+    required: bool,
+    multiple: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    possible_values: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    default: Vec<String>,
+}
+
+impl FlagInfo {
+    fn from_arg(arg: &clap::Arg) -> Self {
+        let takes_value = arg.get_num_args().is_some_and(|n| n.takes_values());
+        Self {
+            name: match arg.get_long() {
+                Some(long) => format!("--{}", long),
+                None => arg.get_id().to_string(),
+            },
+            short: arg.get_short(),
+            help: arg.get_help().map(|h| h.to_string()),
+            takes_value,
+            required: arg.is_required_set(),
+            multiple: arg.get_num_args().is_some_and(|n| n.max_values() > 1)
+                || matches!(arg.get_action(), clap::ArgAction::Append),
+            possible_values: arg
+                .get_possible_values()
+                .iter()
+                .filter(|v| !v.is_hide_set())
+                .map(|v| v.get_name().to_string())
+                .collect(),
+            default: arg
+                .get_default_values()
+                .iter()
+                .map(|v| v.to_string_lossy().into_owned())
+                .collect(),
+        }
+    }
+}
+
+/// Flags a user can pass: help and version are left out
+fn visible_args(command: &clap::Command) -> impl Iterator<Item = &clap::Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && !matches!(arg.get_id().as_str(), "help" | "version"))
+}
+
+impl Capabilities {
+    fn from_command(root: &clap::Command) -> Self {
+        let global_flags = visible_args(root)
+            .filter(|arg| arg.is_global_set())
+            .map(FlagInfo::from_arg)
+            .collect();
+        let commands = root
+            .get_subcommands()
+            .filter(|c| !c.is_hide_set() && c.get_name() != "help")
+            .map(|command| {
+                let (arguments, flags): (Vec<_>, Vec<_>) = visible_args(command)
+                    .filter(|arg| !arg.is_global_set())
+                    .partition(|arg| arg.is_positional());
+                CommandInfo {
+                    name: command.get_name().to_string(),
+                    about: command.get_about().map(|a| a.to_string()),
+                    arguments: arguments.into_iter().map(FlagInfo::from_arg).collect(),
+                    flags: flags.into_iter().map(FlagInfo::from_arg).collect(),
+                }
+            })
+            .collect();
+        Self {
+            version: root.get_version().map(str::to_string),
+            formats: OutputFormat::value_variants()
+                .iter()
+                .filter_map(|f| f.to_possible_value())
+                .map(|v| v.get_name().to_string())
+                .collect(),
+            global_flags,
+            commands,
+        }
+    }
+}
+
+pub fn run(
+    _args: IntrospectArgs,
+    root: &clap::Command,
+    format: OutputFormat,
+    csv_options: &ui::CsvOptions,
+) -> Result<()> {
+    let capabilities = Capabilities::from_command(root);
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&capabilities)?),
+        OutputFormat::Csv => {
+            #[derive(Serialize)]
+            struct FlagRow<'a> {
+                command: &'a str,
+                flag: &'a str,
+                takes_value: bool,
+                help: &'a str,
+            }
+            let mut wtr = csv_options.writer(std::io::stdout())?;
+            let rows = capabilities
+                .global_flags
+                .iter()
+                .map(|flag| ("", flag))
+                .chain(capabilities.commands.iter().flat_map(|c| {
+                    c.arguments
+                        .iter()
+                        .chain(&c.flags)
+                        .map(move |flag| (c.name.as_str(), flag))
+                }));
+            for (command, flag) in rows {
+                wtr.serialize(FlagRow {
+                    command,
+                    flag: &flag.name,
+                    takes_value: flag.takes_value,
+                    help: flag.help.as_deref().unwrap_or_default(),
+                })?;
+            }
+            wtr.flush()?;
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            println!("formats: {}", capabilities.formats.join(", "));
+            for command in &capabilities.commands {
+                let flags: Vec<&str> = command.flags.iter().map(|f| f.name.as_str()).collect();
+                println!("{}  {}", command.name, flags.join(" "));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_split_global_flags_from_command_flags() {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[arg(long, global = true)]
+            dry_run: bool,
+            #[command(subcommand)]
+            command: Sub,
+        }
+        #[derive(clap::Subcommand)]
+        enum Sub {
+            /// Add tags
+            Add {
+                cql: String,
+                #[arg(long)]
+                atomic: bool,
+            },
+        }
+        let mut root = <Cli as clap::CommandFactory>::command();
+        root.build();
+        let caps = Capabilities::from_command(&root);
+        assert!(caps.formats.contains(&"json".to_string()));
+        assert_eq!(caps.global_flags.len(), 1);
+        assert_eq!(caps.global_flags[0].name, "--dry-run");
+        let add = &caps.commands[0];
+        assert_eq!(add.about.as_deref(), Some("Add tags"));
+        assert_eq!(add.arguments[0].name, "cql");
+        assert!(add.arguments[0].required);
+        let flags: Vec<&str> = add.flags.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(flags, vec!["--atomic"]);
+    }
+}
@@ -1,14 +1,464 @@
 pub mod add;
+pub mod build_query;
+pub mod compare_spaces;
+pub mod config;
+pub mod count;
+pub mod doctor;
+pub mod ensure;
+pub mod from_csv;
 pub mod from_json;
 pub mod from_stdin_json;
 pub mod get;
+pub mod introspect;
+pub mod queries;
 pub mod remove;
 pub mod replace;
+pub mod stats;
+pub mod touch;
 
 use crate::ui;
 use anyhow::Result;
+use clap::Args;
 use ctag::api::ConfluenceClient;
-use ctag::models::{OutputFormat, SearchResultItem};
+use ctag::config::Config;
+use ctag::journal::{Journal, JournalEntry};
+use ctag::models::{sanitize_text, ActionDetail, OutputFormat, ProcessResults, SearchResultItem};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Global options shared by every command, resolved from the top-level CLI flags
+pub struct RunOptions {
+    pub dry_run: bool,
+    pub show_progress: bool,
+    pub format: OutputFormat,
+    /// Maximum number of pages to print in dry-run previews
+    pub preview_limit: Option<usize>,
+    pub timing: PhaseTimer,
+    pub csv: ui::CsvOptions,
+    /// Where to record each page's applied changes as it completes
+    pub journal: Option<Journal>,
+    /// Where to record every page the parallel loop finishes, for `--resume`
+    pub checkpoint: Option<Journal>,
+    /// Pages a previous run completed, skipped by the parallel loop
+    pub resume: HashSet<String>,
+    /// Compare requested tags to fetched labels in lowercase, as Confluence
+    /// Cloud stores them
+    pub assume_labels_lowercase: bool,
+    /// Printed instead of the default warning when nothing matches (human formats)
+    pub empty_message: Option<String>,
+    /// Interleave pages by space before applying changes
+    pub fair_by_space: bool,
+    /// Extra delay before each successive worker's first page
+    pub stagger: Option<Duration>,
+    /// Stop starting new pages after this instant
+    pub deadline: Option<Instant>,
+    /// Threads for fetching labels and checking permissions
+    pub read_concurrency: usize,
+    /// Threads for applying changes
+    pub write_concurrency: usize,
+    /// Print a line for each page as soon as its changes are applied
+    pub live: bool,
+}
+
+impl RunOptions {
+    /// Whether a dry-run preview that has already shown `shown` pages should stop
+    pub fn preview_exhausted(&self, shown: usize) -> bool {
+        self.preview_limit.is_some_and(|limit| shown >= limit)
+    }
+
+    /// Whether `--deadline` has passed, so no new work should start
+    pub fn deadline_passed(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Tell a human reader that nothing matched: `--empty-message` on stdout,
+    /// or the default warning
+    pub fn print_empty_message(&self) {
+        match &self.empty_message {
+            Some(message) => println!("{}", message),
+            None => ui::print_warning("No pages found matching the CQL expression."),
+        }
+    }
+
+    /// Report a search that matched nothing and return the empty results.
+    /// Structured formats get a zero summary so every run emits a parseable
+    /// document.
+    pub fn no_pages_found(&self) -> ProcessResults {
+        let results = ProcessResults::new(0);
+        if self.format.is_structured() {
+            ui::print_summary(&results, self.format, &self.csv);
+        } else {
+            self.print_empty_message();
+            if self.dry_run {
+                ui::print_dry_run("No changes will be made.");
+            }
+        }
+        results
+    }
+
+    /// A requested tag in the form it is stored in, for comparing against
+    /// fetched labels
+    pub fn normalize_tag(&self, tag: &str) -> String {
+        if self.assume_labels_lowercase {
+            tag.to_lowercase()
+        } else {
+            tag.to_string()
+        }
+    }
+}
+
+/// Run `op` on a dedicated pool of `threads` threads, so its parallel
+/// iterators use that many workers
+pub fn in_pool<R: Send>(threads: usize, op: impl FnOnce() -> R + Send) -> R {
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool.install(op),
+        Err(e) => {
+            log::warn!("Could not start a thread pool, using the default: {}", e);
+            op()
+        }
+    }
+}
+
+/// Accumulates wall-clock time per phase (search, labels, apply) for `--trace-timing`.
+/// Recording is a no-op when disabled.
+#[derive(Default)]
+pub struct PhaseTimer {
+    enabled: bool,
+    phases: Mutex<Vec<(&'static str, Duration)>>,
+}
+
+impl PhaseTimer {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add the time since `started` to `phase`, keeping first-seen phase order
+    pub fn record(&self, phase: &'static str, started: Instant) {
+        if !self.enabled {
+            return;
+        }
+        let elapsed = started.elapsed();
+        let mut phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        match phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => phases.push((phase, elapsed)),
+        }
+    }
+
+    /// Breakdown like `search: 4.2s, labels: 38.1s`, or `None` when disabled
+    pub fn summary(&self) -> Option<String> {
+        if !self.enabled {
+            return None;
+        }
+        let phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        let parts: Vec<String> = phases
+            .iter()
+            .map(|(name, d)| format!("{}: {:.1}s", name, d.as_secs_f64()))
+            .collect();
+        Some(if parts.is_empty() {
+            "no timed phases".to_string()
+        } else {
+            parts.join(", ")
+        })
+    }
+}
+
+/// Page selection options shared by every command that takes a CQL expression
+#[derive(Args, Default)]
+pub struct QueryArgs {
+    /// Keep only pages whose title matches this regex (applied after the CQL search)
+    #[arg(long, value_name = "REGEX")]
+    pub title_match: Option<String>,
+
+    /// Keep only pages below this page (id or URL) in the page tree, checked
+    /// against each result's ancestors after the CQL search
+    #[arg(long, value_name = "ID")]
+    pub ancestor: Option<String>,
+
+    /// Skip pages that also match this CQL expression
+    #[arg(long, value_name = "CQL")]
+    pub cql_exclude: Option<String>,
+
+    /// Also require the named query from the config file's [queries] table
+    #[arg(long, visible_alias = "query", value_name = "NAME")]
+    pub cql_name: Option<String>,
+
+    /// Only match content of this type; labels on attachments and blog posts
+    /// work the same as on pages
+    #[arg(long, value_name = "TYPE", value_parser = ["page", "blogpost", "attachment"])]
+    pub content_type: Option<String>,
+
+    /// Only match the page with exactly this title; quotes and backslashes
+    /// are escaped for you
+    #[arg(long, value_name = "TITLE")]
+    pub title: Option<String>,
+
+    /// Only match pages in this space (key); repeat for several spaces
+    #[arg(long, value_name = "KEY")]
+    pub space: Vec<String>,
+
+    /// Only search pages carrying a label that starts with this prefix. The
+    /// matching labels are looked up first where Confluence lists them
+    /// (Cloud); elsewhere the search is left as is
+    #[arg(long, value_name = "PREFIX")]
+    pub label_prefix: Option<String>,
+
+    /// Only match pages in the spaces listed in this file, one key per line.
+    /// Long lists are searched in chunks
+    #[arg(long, value_name = "PATH")]
+    pub spaces_file: Option<PathBuf>,
+}
+
+/// Most labels `--label-prefix` puts into one `label in (...)` clause; each
+/// one lengthens the search URL
+const MAX_PREFIX_LABELS: usize = 100;
+
+/// Most `--spaces-file` keys put into one `space in (...)` search
+const MAX_SPACES_PER_SEARCH: usize = 100;
+
+impl QueryArgs {
+    /// The CQL to search with: `cql`, narrowed by the named query, the
+    /// content type, the space and the exact title if given
+    pub fn resolve_cql(&self, cql: &str, config: &Config) -> Result<String> {
+        let mut cql = match &self.cql_name {
+            Some(name) => ctag::cql::and(config.query(name)?, cql),
+            None => cql.to_string(),
+        };
+        if let Some(content_type) = &self.content_type {
+            cql = ctag::cql::and(&cql, &format!("type = {}", content_type));
+        }
+        match self.space.as_slice() {
+            [] => {}
+            [space] => {
+                cql = ctag::cql::and(&cql, &format!("space = {}", ctag::cql::quote(space)));
+            }
+            spaces => cql = ctag::cql::and(&cql, &ctag::cql::space_in(spaces)),
+        }
+        if let Some(title) = &self.title {
+            cql = ctag::cql::and(&cql, &format!("title = {}", ctag::cql::quote(title)));
+        }
+        Ok(cql)
+    }
+
+    /// Narrow `cql` to pages carrying one of the labels that start with
+    /// `--label-prefix`, so pages without any are never fetched
+    pub fn narrow_by_label_prefix(&self, cql: &str, client: &ConfluenceClient) -> Result<String> {
+        let Some(prefix) = &self.label_prefix else {
+            return Ok(cql.to_string());
+        };
+        let labels = match client.labels_with_prefix(prefix)? {
+            Some(labels) => labels,
+            None => {
+                ui::print_warning(&format!(
+                    "This instance doesn't list labels; searching all pages instead of only those with labels starting with '{}'.",
+                    prefix
+                ));
+                return Ok(cql.to_string());
+            }
+        };
+        if labels.len() > MAX_PREFIX_LABELS {
+            ui::print_warning(&format!(
+                "{} labels start with '{}', too many for one search; searching all pages instead.",
+                labels.len(),
+                prefix
+            ));
+            return Ok(cql.to_string());
+        }
+        ui::print_info(&format!(
+            "Narrowed the search to {} labels starting with '{}'.",
+            labels.len(),
+            prefix
+        ));
+        // No label has the prefix: the prefix itself is then not a label
+        // either, so this matches nothing
+        let clause = if labels.is_empty() {
+            format!("label = {}", ctag::cql::quote(prefix))
+        } else {
+            ctag::cql::label_in(&labels)
+        };
+        Ok(ctag::cql::and(cql, &clause))
+    }
+
+    /// Search for the pages matching `cql`. With `--spaces-file` this runs
+    /// one search per chunk of space keys and concatenates the results.
+    pub fn find_pages(
+        &self,
+        client: &ConfluenceClient,
+        cql: &str,
+        expand: &str,
+        format: OutputFormat,
+        show_progress: bool,
+    ) -> Result<Vec<SearchResultItem>> {
+        let Some(keys) = self.spaces_file_keys()? else {
+            return get_matching_pages_expanded(client, cql, 100, expand, format, show_progress);
+        };
+        let mut pages = Vec::new();
+        for search in space_searches(cql, &keys) {
+            pages.extend(get_matching_pages_expanded(
+                client,
+                &search,
+                100,
+                expand,
+                format,
+                show_progress,
+            )?);
+        }
+        Ok(pages)
+    }
+
+    /// Space keys listed in `--spaces-file`, if given
+    fn spaces_file_keys(&self) -> Result<Option<Vec<String>>> {
+        let Some(path) = &self.spaces_file else {
+            return Ok(None);
+        };
+        let file = std::fs::File::open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read spaces file {}: {}", path.display(), e))?;
+        parse_space_lines(std::io::BufReader::new(file))
+            .map(Some)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+    }
+
+    /// Every space key the search was limited to, from `--space` and
+    /// `--spaces-file`, without repeats
+    pub fn space_keys(&self) -> Result<Vec<String>> {
+        let mut keys = self.space.clone();
+        for key in self.spaces_file_keys()?.unwrap_or_default() {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Compile the client-side filters, failing early on invalid input
+    pub fn page_filter(&self) -> Result<PageFilter> {
+        let title = match &self.title_match {
+            Some(pattern) => Some(
+                regex::Regex::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("Invalid regex '{}': {}", pattern, e))?,
+            ),
+            None => None,
+        };
+        let ancestor = self
+            .ancestor
+            .as_deref()
+            .map(ctag::models::page_id_from_ref)
+            .transpose()?;
+        Ok(PageFilter { title, ancestor })
+    }
+}
+
+/// Client-side filters applied to pages after the CQL search
+pub struct PageFilter {
+    title: Option<regex::Regex>,
+    ancestor: Option<String>,
+}
+
+impl PageFilter {
+    /// Keep pages passing every filter. Returns the kept pages and how many were filtered out.
+    pub fn apply(&self, pages: Vec<SearchResultItem>) -> (Vec<SearchResultItem>, usize) {
+        let before = pages.len();
+        let kept: Vec<SearchResultItem> = pages
+            .into_iter()
+            .filter(|page| self.matches(page))
+            .collect();
+        let filtered = before - kept.len();
+        (kept, filtered)
+    }
+
+    fn matches(&self, page: &SearchResultItem) -> bool {
+        if let Some(re) = &self.title {
+            let title = sanitize_text(page.title.as_deref().unwrap_or(""));
+            if !re.is_match(&title) {
+                return false;
+            }
+        }
+        if let Some(ancestor) = &self.ancestor {
+            let ancestors = page.content.as_ref().map(|c| c.ancestors.as_slice());
+            if !ancestors
+                .unwrap_or_default()
+                .iter()
+                .any(|a| a.id.as_deref() == Some(ancestor.as_str()))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Read newline-separated tags from stdin for `--stdin-tags`
+pub fn read_stdin_tags() -> Result<Vec<String>> {
+    parse_tag_lines(std::io::stdin().lock())
+}
+
+/// Trim each line and skip blank ones, dropping repeats. Labels can't contain
+/// whitespace, so such lines are rejected with their line number.
+fn parse_tag_lines<R: std::io::BufRead>(reader: R) -> Result<Vec<String>> {
+    let mut tags: Vec<String> = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let tag = line.trim();
+        if tag.is_empty() {
+            continue;
+        }
+        if tag.contains(char::is_whitespace) {
+            anyhow::bail!(
+                "Invalid tag on stdin line {}: '{}' contains whitespace",
+                i + 1,
+                tag
+            );
+        }
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    if tags.is_empty() {
+        anyhow::bail!("No tags read from stdin");
+    }
+    Ok(tags)
+}
+
+/// Space keys from a `--spaces-file`: one per line, blank lines and `#`
+/// comments skipped, repeats dropped
+fn parse_space_lines<R: std::io::BufRead>(reader: R) -> Result<Vec<String>> {
+    let mut keys: Vec<String> = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let key = line.trim();
+        if key.is_empty() || key.starts_with('#') {
+            continue;
+        }
+        if key.contains(char::is_whitespace) {
+            anyhow::bail!(
+                "Invalid space key on line {}: '{}' contains whitespace",
+                i + 1,
+                key
+            );
+        }
+        if !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+    if keys.is_empty() {
+        anyhow::bail!("No space keys found");
+    }
+    Ok(keys)
+}
+
+/// `cql` narrowed to each chunk of `keys`, one search per chunk
+fn space_searches(cql: &str, keys: &[String]) -> Vec<String> {
+    keys.chunks(MAX_SPACES_PER_SEARCH)
+        .map(|chunk| ctag::cql::and(cql, &ctag::cql::space_in(chunk)))
+        .collect()
+}
 
 /// Shared logic to fetch pages with a spinner progress matching various settings
 pub fn get_matching_pages(
@@ -17,15 +467,90 @@ pub fn get_matching_pages(
     limit: usize,
     format: OutputFormat,
     show_progress: bool,
+) -> Result<Vec<SearchResultItem>> {
+    get_matching_pages_expanded(
+        client,
+        cql,
+        limit,
+        ctag::api::SEARCH_EXPAND,
+        format,
+        show_progress,
+    )
+}
+
+/// `get_matching_pages` with the search results expanded by `expand`, e.g.
+/// `SEARCH_EXPAND_NONE` when only ids and titles are needed
+pub fn get_matching_pages_expanded(
+    client: &ConfluenceClient,
+    cql: &str,
+    limit: usize,
+    expand: &str,
+    format: OutputFormat,
+    show_progress: bool,
+) -> Result<Vec<SearchResultItem>> {
+    fetch_pages(
+        client,
+        cql,
+        limit,
+        expand,
+        format,
+        show_progress,
+        &format!("Finding pages matching: {}", cql),
+    )
+}
+
+/// Drop pages that also match `exclude_cql`. Returns the kept pages and how
+/// many were excluded.
+pub fn exclude_pages(
+    client: &ConfluenceClient,
+    pages: Vec<SearchResultItem>,
+    exclude_cql: Option<&str>,
+    format: OutputFormat,
+    show_progress: bool,
+) -> Result<(Vec<SearchResultItem>, usize)> {
+    let Some(exclude_cql) = exclude_cql else {
+        return Ok((pages, 0));
+    };
+    if client.raw_search_path().is_some() {
+        anyhow::bail!("--cql-exclude can't be combined with --raw-search-path");
+    }
+    // Only the ids are needed
+    let excluded: HashSet<String> = fetch_pages(
+        client,
+        exclude_cql,
+        100,
+        ctag::api::SEARCH_EXPAND_NONE,
+        format,
+        show_progress,
+        "Finding pages to exclude",
+    )?
+    .iter()
+    .filter_map(|page| page.page_id().map(str::to_string))
+    .collect();
+    let before = pages.len();
+    let kept: Vec<SearchResultItem> = pages
+        .into_iter()
+        .filter(|page| page.page_id().is_none_or(|id| !excluded.contains(id)))
+        .collect();
+    let dropped = before - kept.len();
+    Ok((kept, dropped))
+}
+
+/// Run a paginated search, with a spinner labeled `label` when appropriate
+fn fetch_pages(
+    client: &ConfluenceClient,
+    cql: &str,
+    limit: usize,
+    expand: &str,
+    format: OutputFormat,
+    show_progress: bool,
+    label: &str,
 ) -> Result<Vec<SearchResultItem>> {
     let verbose = format.is_verbose();
     let is_structured = format.is_structured();
 
     let spinner = if (verbose || !show_progress) && !is_structured {
-        Some(ui::create_pagination_spinner(&format!(
-            "Finding pages matching: {}",
-            cql
-        )))
+        Some(ui::create_pagination_spinner(label))
     } else {
         None
     };
@@ -34,12 +559,21 @@ pub fn get_matching_pages(
         client.get_all_cql_results_with_progress(
             cql,
             limit,
-            Some(|count, _| {
+            expand,
+            Some(|count: usize, total: Option<usize>| {
+                if let Some(total) = total {
+                    ui::set_pagination_total(pb, total.max(count) as u64);
+                }
                 pb.set_position(count as u64);
             }),
         )?
     } else {
-        client.get_all_cql_results(cql, limit)?
+        client.get_all_cql_results_with_progress(
+            cql,
+            limit,
+            expand,
+            None::<fn(usize, Option<usize>)>,
+        )?
     };
 
     if let Some(s) = spinner {
@@ -49,20 +583,390 @@ pub fn get_matching_pages(
     Ok(pages)
 }
 
+/// With `enabled`, drop pages the user cannot edit so writes don't fail with 403.
+/// Checks run on `threads` workers. Returns the kept pages and how many were
+/// dropped.
+pub fn retain_editable(
+    client: &ConfluenceClient,
+    pages: Vec<SearchResultItem>,
+    enabled: bool,
+    threads: usize,
+) -> (Vec<SearchResultItem>, usize) {
+    use rayon::prelude::*;
+
+    if !enabled {
+        return (pages, 0);
+    }
+    let before = pages.len();
+    let kept: Vec<SearchResultItem> = in_pool(threads, || {
+        pages
+            .into_par_iter()
+            .filter(|page| {
+                let Some(page_id) = page.page_id() else {
+                    return true;
+                };
+                match client.can_edit_page(page_id) {
+                    Ok(true) => true,
+                    Ok(false) => {
+                        log::info!("Skipping page {}: no edit permission", page_id);
+                        false
+                    }
+                    // Let the write itself report the problem
+                    Err(e) => {
+                        ui::defer_warning(format!(
+                            "Could not check edit permission on page {}: {:#}",
+                            page_id, e
+                        ));
+                        true
+                    }
+                }
+            })
+            .collect()
+    });
+    let dropped = before - kept.len();
+    if dropped > 0 {
+        ui::print_warning(&format!(
+            "Skipping {} pages you don't have permission to edit.",
+            dropped
+        ));
+    }
+    (kept, dropped)
+}
+
+/// Re-read the pages a run changed and print the labels they have now, flagging
+/// any added label that is missing or removed label that is still there. For
+/// `--review`.
+pub fn review_changes(client: &ConfluenceClient, details: &[ActionDetail], opts: &RunOptions) {
+    let changed: Vec<&ActionDetail> = details
+        .iter()
+        .filter(|d| !d.tags_added.is_empty() || !d.tags_removed.is_empty())
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    ui::print_step(&format!("Review of {} changed pages:", changed.len()));
+    for detail in changed {
+        ui::print_page_action("Now", &detail.url, &detail.space);
+        match client.get_page_tags(&detail.page_id) {
+            Ok(current) => {
+                ui::print_substep(&current.join(", "));
+                for issue in review_issues(detail, &current, |tag| opts.normalize_tag(tag)) {
+                    ui::print_warning(&issue);
+                }
+            }
+            Err(e) => ui::print_warning(&format!("Could not re-read tags: {:#}", e)),
+        }
+    }
+}
+
+/// Where a page's current labels disagree with what the run changed, both
+/// sides compared after `normalize`
+fn review_issues(
+    detail: &ActionDetail,
+    current: &[String],
+    normalize: impl Fn(&str) -> String,
+) -> Vec<String> {
+    let current: Vec<String> = current.iter().map(|tag| normalize(tag)).collect();
+    let missing = detail
+        .tags_added
+        .iter()
+        .filter(|tag| !current.contains(&normalize(tag)))
+        .map(|tag| format!("'{}' was added but is missing", tag));
+    let lingering = detail
+        .tags_removed
+        .iter()
+        .filter(|tag| current.contains(&normalize(tag)))
+        .map(|tag| format!("'{}' was removed but is still present", tag));
+    missing.chain(lingering).collect()
+}
+
+/// One run's summary and per-page details, written by `--report-file`
+#[derive(Serialize)]
+struct RunReport<'a> {
+    command: &'a str,
+    cql: Option<&'a str>,
+    timestamp: String,
+    summary: serde_json::Value,
+    details: &'a [ActionDetail],
+}
+
+impl<'a> RunReport<'a> {
+    fn new(command: &'a str, cql: Option<&'a str>, results: &'a ProcessResults) -> Result<Self> {
+        let mut summary = serde_json::to_value(results)?;
+        if let Some(fields) = summary.as_object_mut() {
+            fields.remove("details");
+        }
+        Ok(Self {
+            command,
+            cql,
+            timestamp: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+            summary,
+            details: &results.details,
+        })
+    }
+}
+
+/// Write the run's report as JSON to `path`, whatever the output format
+pub fn write_report(
+    path: &Path,
+    command: &str,
+    cql: Option<&str>,
+    results: &ProcessResults,
+) -> Result<()> {
+    let report = RunReport::new(command, cql, results)?;
+    std::fs::write(path, serde_json::to_string_pretty(&report)?)
+        .map_err(|e| anyhow::anyhow!("Failed to write report file {}: {}", path.display(), e))
+}
+
+/// The run's counts and duration in the Prometheus text exposition format
+fn format_metrics(command: &str, results: &ProcessResults, duration: Duration) -> String {
+    let metrics = [
+        ("pages_total", "Pages matched", results.total as f64),
+        (
+            "pages_success",
+            "Pages changed successfully",
+            results.success as f64,
+        ),
+        ("pages_failed", "Pages that failed", results.failed as f64),
+        ("tags_added", "Tags added", results.tags_added as f64),
+        ("tags_removed", "Tags removed", results.tags_removed as f64),
+        ("duration_seconds", "Run duration", duration.as_secs_f64()),
+    ];
+    let mut out = String::new();
+    for (name, help, value) in metrics {
+        out.push_str(&format!(
+            "# HELP ctag_{name} {help}\n# TYPE ctag_{name} gauge\nctag_{name}{{command=\"{command}\"}} {value}\n"
+        ));
+    }
+    out
+}
+
+/// Write the run's metrics to `path` for the node_exporter textfile
+/// collector. The file is renamed into place so a scrape never sees it half
+/// written.
+pub fn write_metrics(
+    path: &Path,
+    command: &str,
+    results: &ProcessResults,
+    duration: Duration,
+) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, format_metrics(command, results, duration))
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| anyhow::anyhow!("Failed to write metrics file {}: {}", path.display(), e))
+}
+
+/// Wait `delay`, then search for the changed pages again and report how many
+/// the search index already shows with their new labels. For `--verify-after`.
+pub fn report_search_visibility(
+    client: &ConfluenceClient,
+    details: &[ActionDetail],
+    delay: Duration,
+) {
+    let changed: Vec<&ActionDetail> = details
+        .iter()
+        .filter(|d| !d.tags_added.is_empty() || !d.tags_removed.is_empty())
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    std::thread::sleep(delay);
+    match labelled_in_search(client, &changed) {
+        Ok(labelled) => {
+            let visible = changed
+                .iter()
+                .filter(|d| search_reflects(d, &labelled))
+                .count();
+            let msg = format!(
+                "Search shows {} of {} changed pages with their new labels after {}.",
+                visible,
+                changed.len(),
+                humantime::format_duration(delay)
+            );
+            if visible < changed.len() {
+                ui::print_warning(&msg);
+            } else {
+                ui::print_info(&msg);
+            }
+        }
+        Err(e) => ui::print_warning(&format!("Could not verify changes in search: {:#}", e)),
+    }
+}
+
+/// `(tag, page id)` pairs the search index returns among the changed pages
+fn labelled_in_search(
+    client: &ConfluenceClient,
+    changed: &[&ActionDetail],
+) -> Result<HashSet<(String, String)>> {
+    let mut by_tag: HashMap<&str, Vec<&str>> = HashMap::new();
+    for detail in changed {
+        for tag in detail.tags_added.iter().chain(&detail.tags_removed) {
+            by_tag.entry(tag).or_default().push(&detail.page_id);
+        }
+    }
+    let mut labelled = HashSet::new();
+    for (tag, ids) in by_tag {
+        for chunk in ids.chunks(100) {
+            let cql = format!(
+                "label = {} AND id in ({})",
+                ctag::cql::quote(tag),
+                chunk.join(",")
+            );
+            for page in client.get_all_cql_results_with_progress(
+                &cql,
+                100,
+                ctag::api::SEARCH_EXPAND_NONE,
+                None::<fn(usize, Option<usize>)>,
+            )? {
+                if let Some(id) = page.page_id() {
+                    labelled.insert((tag.to_string(), id.to_string()));
+                }
+            }
+        }
+    }
+    Ok(labelled)
+}
+
+/// Whether search shows every added tag and none of the removed ones
+fn search_reflects(detail: &ActionDetail, labelled: &HashSet<(String, String)>) -> bool {
+    let found = |tag: &String| labelled.contains(&(tag.clone(), detail.page_id.clone()));
+    detail.tags_added.iter().all(found) && !detail.tags_removed.iter().any(found)
+}
+
 pub enum ActionResult {
     Success {
         added: usize,
         removed: usize,
         detail: Option<ctag::models::ActionDetail>,
     },
-    Failed,
+    Failed {
+        detail: Option<ctag::models::ActionDetail>,
+    },
     Skipped,
 }
 
+/// Delay before the `started`-th page of a parallel apply. Only the first
+/// page of each worker waits, `stagger` longer per worker, so the initial
+/// burst is spread out while later pages run at full speed.
+fn stagger_delay(started: usize, workers: usize, stagger: Duration) -> Option<Duration> {
+    (started > 0 && started < workers).then(|| stagger * started as u32)
+}
+
+/// Pages left to change after `--confirm-each-space`
+pub struct SpaceApproval {
+    pub pages: Vec<SearchResultItem>,
+    /// Pages in the spaces the user declined
+    pub declined: usize,
+    /// The user chose abort, so no page is changed
+    pub aborted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SpaceChoice {
+    Apply,
+    Skip,
+    Abort,
+}
+
+fn parse_space_choice(answer: &str) -> Option<SpaceChoice> {
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Some(SpaceChoice::Apply),
+        "n" | "no" => Some(SpaceChoice::Skip),
+        "a" | "abort" => Some(SpaceChoice::Abort),
+        _ => None,
+    }
+}
+
+/// The space a page is approved under: its key, or its name when the
+/// result doesn't carry the key. Names aren't unique across spaces.
+fn approval_space(page: &SearchResultItem) -> &str {
+    page.space_key().unwrap_or_else(|| page.space_name())
+}
+
+/// `(space, page count)` in order of first appearance, keyed by
+/// `approval_space`
+fn pages_per_space(pages: &[SearchResultItem]) -> Vec<(&str, usize)> {
+    let mut spaces: Vec<(&str, usize)> = Vec::new();
+    for page in pages {
+        let space = approval_space(page);
+        match spaces.iter_mut().find(|(s, _)| *s == space) {
+            Some((_, count)) => *count += 1,
+            None => spaces.push((space, 1)),
+        }
+    }
+    spaces
+}
+
+/// Ask once per space whether to change its pages, before any page is
+/// changed. Pages in declined spaces are dropped and counted; abort (or a
+/// closed prompt) drops them all.
+pub fn confirm_each_space(pages: Vec<SearchResultItem>) -> SpaceApproval {
+    let mut approved: HashSet<String> = HashSet::new();
+    for (space, count) in pages_per_space(&pages) {
+        let prompt = format!("Apply to {} pages in space {}? [y/n/abort]", count, space);
+        let choice = loop {
+            let answer = dialoguer::Input::<String>::new()
+                .with_prompt(&prompt)
+                .interact_text();
+            match answer.map(|a| parse_space_choice(&a)) {
+                Ok(Some(choice)) => break choice,
+                Ok(None) => ui::print_warning("Answer y, n or abort."),
+                Err(_) => break SpaceChoice::Abort,
+            }
+        };
+        match choice {
+            SpaceChoice::Apply => {
+                approved.insert(space.to_string());
+            }
+            SpaceChoice::Skip => {}
+            SpaceChoice::Abort => {
+                return SpaceApproval {
+                    declined: pages.len(),
+                    pages: Vec::new(),
+                    aborted: true,
+                };
+            }
+        }
+    }
+    let total = pages.len();
+    let pages: Vec<SearchResultItem> = pages
+        .into_iter()
+        .filter(|page| approved.contains(approval_space(page)))
+        .collect();
+    SpaceApproval {
+        declined: total - pages.len(),
+        pages,
+        aborted: false,
+    }
+}
+
+/// Round-robin pages across spaces (in order of first appearance) so a
+/// multi-space run spreads its writes instead of working one space at a time
+fn interleave_by_space(pages: &[SearchResultItem]) -> Vec<&SearchResultItem> {
+    let mut groups: Vec<(&str, VecDeque<&SearchResultItem>)> = Vec::new();
+    for page in pages {
+        let space = page.space_name();
+        match groups.iter_mut().find(|(s, _)| *s == space) {
+            Some((_, group)) => group.push_back(page),
+            None => groups.push((space, VecDeque::from([page]))),
+        }
+    }
+    let mut ordered = Vec::with_capacity(pages.len());
+    while ordered.len() < pages.len() {
+        for (_, group) in groups.iter_mut() {
+            if let Some(page) = group.pop_front() {
+                ordered.push(page);
+            }
+        }
+    }
+    ordered
+}
+
 /// Shared logic for processing pages in parallel with progress bar
 pub fn process_pages_parallel<F>(
     pages: &[SearchResultItem],
-    show_progress: bool,
+    opts: &RunOptions,
     action: F,
 ) -> ctag::models::ProcessResults
 where
@@ -70,63 +974,515 @@ where
 {
     use rayon::prelude::*;
     use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::Mutex;
 
-    let progress = if show_progress {
+    let progress = if opts.show_progress {
         Some(ui::create_progress_bar(pages.len() as u64))
     } else {
         None
     };
+    let journal = |detail: &ctag::models::ActionDetail, success: bool| {
+        if let Some(journal) = &opts.journal {
+            if let Err(e) = journal.record(&JournalEntry::from_detail(detail, success)) {
+                ui::print_error(&format!("Failed to write journal: {:#}", e));
+            }
+        }
+    };
+    let checkpoint = |page: &SearchResultItem, detail: Option<&ActionDetail>, success: bool| {
+        let (Some(checkpoint), Some(page_id)) = (&opts.checkpoint, page.page_id()) else {
+            return;
+        };
+        let entry = match detail {
+            Some(detail) => JournalEntry::from_detail(detail, success),
+            None => JournalEntry::unchanged(
+                page_id,
+                page.title.as_deref().unwrap_or("Unknown"),
+                success,
+            ),
+        };
+        if let Err(e) = checkpoint.record(&entry) {
+            ui::print_error(&format!("Failed to write checkpoint: {:#}", e));
+        }
+    };
 
     let success_count = AtomicUsize::new(0);
     let failed_count = AtomicUsize::new(0);
     let skipped_count = AtomicUsize::new(0);
     let added_count = AtomicUsize::new(0);
     let removed_count = AtomicUsize::new(0);
+    let unstarted_count = AtomicUsize::new(0);
+    let resumed_count = AtomicUsize::new(0);
+    let started_count = AtomicUsize::new(0);
     let details = Mutex::new(Vec::new());
 
-    pages.par_iter().for_each(|page| {
-        match action(page) {
-            ActionResult::Success {
-                added,
-                removed,
-                detail,
-            } => {
-                success_count.fetch_add(1, Ordering::Relaxed);
-                added_count.fetch_add(added, Ordering::Relaxed);
-                removed_count.fetch_add(removed, Ordering::Relaxed);
-                if let Some(d) = detail {
-                    if let Ok(mut g) = details.lock() {
-                        g.push(d);
-                    }
+    let ordered: Vec<&SearchResultItem> = if opts.fair_by_space {
+        interleave_by_space(pages)
+    } else {
+        pages.iter().collect()
+    };
+
+    in_pool(opts.write_concurrency, || {
+        ordered.par_iter().for_each(|page| {
+            // Past the deadline, drain the remaining pages without touching them
+            if opts.deadline_passed() {
+                unstarted_count.fetch_add(1, Ordering::Relaxed);
+                if let Some(ref p) = progress {
+                    p.inc(1);
                 }
+                return;
             }
-            ActionResult::Failed => {
-                failed_count.fetch_add(1, Ordering::Relaxed);
+            if page.page_id().is_some_and(|id| opts.resume.contains(id)) {
+                resumed_count.fetch_add(1, Ordering::Relaxed);
+                if let Some(ref p) = progress {
+                    p.inc(1);
+                }
+                return;
             }
-            ActionResult::Skipped => {
-                skipped_count.fetch_add(1, Ordering::Relaxed);
+            if let Some(stagger) = opts.stagger {
+                let started = started_count.fetch_add(1, Ordering::Relaxed);
+                if let Some(delay) = stagger_delay(started, rayon::current_num_threads(), stagger) {
+                    std::thread::sleep(delay);
+                }
+            }
+            match action(page) {
+                ActionResult::Success {
+                    added,
+                    removed,
+                    detail,
+                } => {
+                    success_count.fetch_add(1, Ordering::Relaxed);
+                    added_count.fetch_add(added, Ordering::Relaxed);
+                    removed_count.fetch_add(removed, Ordering::Relaxed);
+                    checkpoint(page, detail.as_ref(), true);
+                    if let Some(d) = detail {
+                        journal(&d, true);
+                        if opts.live {
+                            ui::print_live_result(progress.as_ref(), &d, true);
+                        }
+                        if let Ok(mut g) = details.lock() {
+                            g.push(d);
+                        }
+                    }
+                }
+                ActionResult::Failed { detail } => {
+                    failed_count.fetch_add(1, Ordering::Relaxed);
+                    checkpoint(page, detail.as_ref(), false);
+                    if let Some(d) = detail {
+                        journal(&d, false);
+                        if opts.live {
+                            ui::print_live_result(progress.as_ref(), &d, false);
+                        }
+                        if let Ok(mut g) = details.lock() {
+                            g.push(d);
+                        }
+                    }
+                }
+                ActionResult::Skipped => {
+                    skipped_count.fetch_add(1, Ordering::Relaxed);
+                    checkpoint(page, None, true);
+                }
             }
-        }
 
-        if let Some(ref p) = progress {
-            p.inc(1);
-        }
+            if let Some(ref p) = progress {
+                p.inc(1);
+            }
+        })
     });
 
     if let Some(ref p) = progress {
         p.finish_with_message("Done");
     }
 
+    let resumed = resumed_count.load(Ordering::Relaxed);
+    if resumed > 0 {
+        ui::print_info(&format!(
+            "Skipped {} pages already completed according to --resume.",
+            resumed
+        ));
+    }
+    let unstarted = unstarted_count.load(Ordering::Relaxed);
     ctag::models::ProcessResults {
         total: pages.len(),
-        processed: pages.len(),
-        skipped: skipped_count.load(Ordering::Relaxed),
+        processed: pages.len() - unstarted,
+        skipped: skipped_count.load(Ordering::Relaxed) + resumed,
         success: success_count.load(Ordering::Relaxed),
         failed: failed_count.load(Ordering::Relaxed),
         aborted: false,
+        deadline_reached: unstarted > 0,
         tags_added: added_count.load(Ordering::Relaxed),
         tags_removed: removed_count.load(Ordering::Relaxed),
+        added_only: 0,
         details: details.into_inner().unwrap_or_default(),
+        distinct_pages: 0,
+        page_ids: HashSet::new(),
+        subtotals: Vec::new(),
+        dry_run: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(id: &str, title: &str) -> SearchResultItem {
+        SearchResultItem {
+            content: Some(ctag::models::Content {
+                id: Some(id.to_string()),
+                title: Some(title.to_string()),
+                content_type: Some("page".to_string()),
+                status: None,
+                space: None,
+                ancestors: vec![],
+                version: None,
+                history: None,
+                metadata: None,
+            }),
+            title: Some(title.to_string()),
+            space: None,
+            result_global_container: None,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn title_match_filters_and_counts_skipped() {
+        let query = QueryArgs {
+            title_match: Some(r"^RFC-\d+".to_string()),
+            ..Default::default()
+        };
+        let filter = query.page_filter().unwrap();
+        let pages = vec![
+            page("1", "RFC-12 Auth"),
+            page("2", "Meeting notes"),
+            page("3", "RFC-7 &amp; friends"),
+        ];
+        let (kept, filtered) = filter.apply(pages);
+        assert_eq!(filtered, 1);
+        let ids: Vec<_> = kept.iter().filter_map(|p| p.page_id()).collect();
+        assert_eq!(ids, vec!["1", "3"]);
+    }
+
+    #[test]
+    fn ancestor_filter_keeps_pages_below_the_given_page() {
+        let query = QueryArgs {
+            ancestor: Some("100".to_string()),
+            ..Default::default()
+        };
+        let filter = query.page_filter().unwrap();
+        let mut below = page("1", "Child");
+        below.content.as_mut().unwrap().ancestors = vec![
+            ctag::models::Ancestor {
+                id: Some("100".to_string()),
+                title: Some("Policies".to_string()),
+            },
+            ctag::models::Ancestor {
+                id: Some("200".to_string()),
+                title: Some("Security".to_string()),
+            },
+        ];
+        let (kept, filtered) = filter.apply(vec![below, page("2", "Elsewhere")]);
+        assert_eq!(filtered, 1);
+        assert_eq!(kept[0].page_id(), Some("1"));
+    }
+
+    #[test]
+    fn parse_tag_lines_trims_dedups_and_validates() {
+        let tags = parse_tag_lines("  alpha\n\nbeta\r\nalpha\n".as_bytes()).unwrap();
+        assert_eq!(tags, vec!["alpha", "beta"]);
+
+        let err = parse_tag_lines("ok\nnot ok\n".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+        assert!(parse_tag_lines("\n  \n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn spaces_file_is_parsed_and_searched_in_chunks() {
+        let keys = parse_space_lines("# team spaces\nDOCS\n\n ENG \nDOCS\n".as_bytes()).unwrap();
+        assert_eq!(keys, vec!["DOCS", "ENG"]);
+        assert!(parse_space_lines("# nothing\n".as_bytes()).is_err());
+        assert!(parse_space_lines("MY SPACE\n".as_bytes()).is_err());
+
+        assert_eq!(
+            space_searches("label = x", &keys),
+            vec![r#"(label = x) AND (space in ("DOCS", "ENG"))"#]
+        );
+        let many: Vec<String> = (0..250).map(|i| format!("S{}", i)).collect();
+        let searches = space_searches("", &many);
+        assert_eq!(searches.len(), 3);
+        assert!(searches[2].starts_with(r#"space in ("S200", "#));
+    }
+
+    #[test]
+    fn phase_timer_accumulates_in_first_seen_order() {
+        let timer = PhaseTimer::new(true);
+        let started = Instant::now();
+        timer.record("search", started);
+        timer.record("apply", started);
+        timer.record("search", started);
+        let summary = timer.summary().unwrap();
+        assert!(summary.starts_with("search: "));
+        assert!(summary.contains(", apply: "));
+        assert_eq!(summary.matches("search").count(), 1);
+
+        assert_eq!(PhaseTimer::new(false).summary(), None);
+    }
+
+    #[test]
+    fn title_match_rejects_invalid_regex() {
+        let query = QueryArgs {
+            title_match: Some("(".to_string()),
+            ..Default::default()
+        };
+        let err = query.page_filter().err().unwrap();
+        assert!(err.to_string().contains("Invalid regex"));
+    }
+
+    #[test]
+    fn search_reflects_requires_adds_present_and_removals_gone() {
+        let detail = ActionDetail {
+            page_id: "1".to_string(),
+            title: "Page".to_string(),
+            space: "Docs".to_string(),
+            url: String::new(),
+            tags_added: vec!["new".to_string()],
+            tags_removed: vec!["old".to_string()],
+            tag_results: Vec::new(),
+        };
+        let pair = |tag: &str| (tag.to_string(), "1".to_string());
+        assert!(search_reflects(&detail, &HashSet::from([pair("new")])));
+        assert!(!search_reflects(&detail, &HashSet::new()));
+        assert!(!search_reflects(
+            &detail,
+            &HashSet::from([pair("new"), pair("old")])
+        ));
+    }
+
+    #[test]
+    fn run_report_moves_details_out_of_the_summary() {
+        let mut results = ProcessResults::new(1);
+        results.details.push(ActionDetail {
+            page_id: "1".to_string(),
+            title: "Page".to_string(),
+            space: "Docs".to_string(),
+            url: String::new(),
+            tags_added: vec!["a".to_string()],
+            tags_removed: Vec::new(),
+            tag_results: Vec::new(),
+        });
+        let report = RunReport::new("add", Some("space = DOCS"), &results).unwrap();
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["command"], "add");
+        assert_eq!(json["summary"]["total"], 1);
+        assert!(json["summary"].get("details").is_none());
+        assert_eq!(json["details"][0]["page_id"], "1");
+    }
+
+    #[test]
+    fn interleave_by_space_round_robins_spaces() {
+        let in_space = |id: &str, space: &str| {
+            let mut p = page(id, "T");
+            p.space = Some(ctag::models::Space {
+                id: None,
+                key: None,
+                name: Some(space.to_string()),
+            });
+            p
+        };
+        let pages = vec![
+            in_space("1", "A"),
+            in_space("2", "A"),
+            in_space("3", "A"),
+            in_space("4", "B"),
+            in_space("5", "C"),
+        ];
+        let ids: Vec<&str> = interleave_by_space(&pages)
+            .iter()
+            .filter_map(|p| p.page_id())
+            .collect();
+        assert_eq!(ids, vec!["1", "4", "5", "2", "3"]);
+    }
+
+    #[test]
+    fn spaces_are_counted_for_confirmation_in_order() {
+        let in_space = |id: &str, key: Option<&str>, name: &str| {
+            let mut p = page(id, "T");
+            p.space = Some(ctag::models::Space {
+                id: None,
+                key: key.map(str::to_string),
+                name: Some(name.to_string()),
+            });
+            p
+        };
+        let pages = vec![
+            in_space("1", Some("DOCS"), "Documentation"),
+            in_space("2", None, "Scratch"),
+            in_space("3", Some("DOCS"), "Documentation"),
+            // Same display name, different space
+            in_space("4", Some("DOCS2"), "Documentation"),
+        ];
+        assert_eq!(
+            pages_per_space(&pages),
+            vec![("DOCS", 2), ("Scratch", 1), ("DOCS2", 1)]
+        );
+        assert_eq!(parse_space_choice(" Y "), Some(SpaceChoice::Apply));
+        assert_eq!(parse_space_choice("no"), Some(SpaceChoice::Skip));
+        assert_eq!(parse_space_choice("abort"), Some(SpaceChoice::Abort));
+        assert_eq!(parse_space_choice("maybe"), None);
+    }
+
+    #[test]
+    fn stagger_delay_offsets_only_first_pages() {
+        let step = Duration::from_millis(50);
+        assert_eq!(stagger_delay(0, 4, step), None);
+        assert_eq!(stagger_delay(1, 4, step), Some(Duration::from_millis(50)));
+        assert_eq!(stagger_delay(3, 4, step), Some(Duration::from_millis(150)));
+        assert_eq!(stagger_delay(4, 4, step), None);
+    }
+
+    #[test]
+    fn review_issues_flag_changes_that_did_not_land() {
+        let detail = ActionDetail {
+            page_id: "1".to_string(),
+            title: "Page".to_string(),
+            space: "Docs".to_string(),
+            url: String::new(),
+            tags_added: vec!["new".to_string()],
+            tags_removed: vec!["old".to_string()],
+            tag_results: Vec::new(),
+        };
+        assert!(review_issues(&detail, &["new".to_string()], str::to_string).is_empty());
+        assert_eq!(
+            review_issues(&detail, &["old".to_string()], str::to_string).len(),
+            2
+        );
+        // Labels stored lowercase still match what was asked for
+        let detail = ActionDetail {
+            tags_added: vec!["Draft".to_string()],
+            ..detail
+        };
+        assert!(review_issues(&detail, &["draft".to_string()], str::to_lowercase).is_empty());
+    }
+
+    #[test]
+    fn format_metrics_labels_each_metric_by_command() {
+        let mut results = ProcessResults::new(4);
+        results.success = 3;
+        results.failed = 1;
+        results.tags_added = 6;
+        let text = format_metrics("add", &results, Duration::from_millis(1500));
+        assert!(text.contains("# TYPE ctag_pages_total gauge\n"));
+        assert!(text.contains("ctag_pages_total{command=\"add\"} 4\n"));
+        assert!(text.contains("ctag_pages_failed{command=\"add\"} 1\n"));
+        assert!(text.contains("ctag_tags_added{command=\"add\"} 6\n"));
+        assert!(text.contains("ctag_duration_seconds{command=\"add\"} 1.5\n"));
+    }
+
+    #[test]
+    fn bounded_pool_counts_every_page() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let opts = RunOptions {
+            dry_run: false,
+            show_progress: true,
+            format: OutputFormat::Json,
+            preview_limit: None,
+            timing: PhaseTimer::new(false),
+            csv: ui::CsvOptions::default(),
+            journal: None,
+            checkpoint: None,
+            resume: HashSet::new(),
+            assume_labels_lowercase: false,
+            empty_message: None,
+            fair_by_space: false,
+            stagger: None,
+            deadline: None,
+            read_concurrency: 8,
+            write_concurrency: 2,
+            live: false,
+        };
+        let pages: Vec<SearchResultItem> = (0..20).map(|i| page(&i.to_string(), "Page")).collect();
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let results = process_pages_parallel(&pages, &opts, |page| {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(5));
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            if page.page_id() == Some("0") {
+                ActionResult::Skipped
+            } else {
+                ActionResult::Success {
+                    added: 1,
+                    removed: 0,
+                    detail: None,
+                }
+            }
+        });
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert_eq!(results.processed, 20);
+        assert_eq!(results.success, 19);
+        assert_eq!(results.skipped, 1);
+        assert_eq!(results.tags_added, 19);
+    }
+
+    #[test]
+    fn in_pool_limits_worker_threads() {
+        assert_eq!(in_pool(2, rayon::current_num_threads), 2);
+        assert_eq!(in_pool(5, rayon::current_num_threads), 5);
+    }
+
+    #[test]
+    fn cql_name_is_anded_with_the_positional_cql() {
+        let config = Config::parse("[queries]\ndocs = \"space = DOCS\"\n").unwrap();
+        let query = QueryArgs {
+            cql_name: Some("docs".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            query.resolve_cql("label = draft", &config).unwrap(),
+            "(space = DOCS) AND (label = draft)"
+        );
+        assert_eq!(
+            QueryArgs::default()
+                .resolve_cql("label = draft", &config)
+                .unwrap(),
+            "label = draft"
+        );
+        let attachments = QueryArgs {
+            content_type: Some("attachment".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            attachments.resolve_cql("space = DOCS", &config).unwrap(),
+            "(space = DOCS) AND (type = attachment)"
+        );
+    }
+
+    #[test]
+    fn title_and_space_build_escaped_clauses() {
+        let config = Config::default();
+        let query = QueryArgs {
+            space: vec!["DOCS".to_string()],
+            title: Some(r#"Release "2.0" C:\notes"#.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            query.resolve_cql("", &config).unwrap(),
+            r#"(space = "DOCS") AND (title = "Release \"2.0\" C:\\notes")"#
+        );
+        assert_eq!(
+            query.resolve_cql("type = page", &config).unwrap(),
+            r#"((type = page) AND (space = "DOCS")) AND (title = "Release \"2.0\" C:\\notes")"#
+        );
+
+        let several = QueryArgs {
+            space: vec!["DOCS".to_string(), "ENG".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            several
+                .resolve_cql("type = page", &Config::default())
+                .unwrap(),
+            r#"(type = page) AND (space in ("DOCS", "ENG"))"#
+        );
     }
 }
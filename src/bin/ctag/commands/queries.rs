@@ -0,0 +1,52 @@
+use crate::ui;
+use anyhow::Result;
+use clap::Args;
+use ctag::config::Config;
+use ctag::models::OutputFormat;
+use serde::Serialize;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # List the named queries from the config file
+  ctag queries
+
+  # Run one of them
+  ctag get --query stale-docs 'type = page'
+")]
+pub struct QueriesArgs {}
+
+#[derive(Serialize)]
+struct QueryRow<'a> {
+    name: &'a str,
+    cql: &'a str,
+}
+
+pub fn run(
+    _args: QueriesArgs,
+    format: OutputFormat,
+    config: &Config,
+    csv_options: &ui::CsvOptions,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&config.queries)?),
+        OutputFormat::Csv => {
+            let mut wtr = csv_options.writer(std::io::stdout())?;
+            for (name, cql) in &config.queries {
+                wtr.serialize(QueryRow { name, cql })?;
+            }
+            wtr.flush()?;
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            if config.queries.is_empty() {
+                ui::print_info("No named queries. Add a [queries] table to the config file.");
+                return Ok(());
+            }
+            let width = config.queries.keys().map(String::len).max().unwrap_or(0);
+            for (name, cql) in &config.queries {
+                println!("{:width$}  {}", name, cql, width = width);
+            }
+        }
+    }
+    Ok(())
+}
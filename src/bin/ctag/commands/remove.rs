@@ -1,11 +1,13 @@
+use crate::commands::RunOptions;
 use crate::ui;
 use anyhow::Result;
 use clap::Args;
 use colored::Colorize;
 use ctag::api::ConfluenceClient;
 use ctag::models::sanitize_text;
-use ctag::models::ProcessResults;
+use ctag::models::{ProcessResults, TagResult};
 use dialoguer::Confirm;
+use std::time::Instant;
 
 #[derive(Args)]
 #[command(after_help = "\
@@ -24,15 +26,22 @@ EXAMPLES:
 
   # Remove all tags starting with 'v1-'
   ctag remove --regex 'label = migration' 'v1-.*'
+
+  # Remove tags listed in a file, one per line
+  ctag remove 'space = DOCS' --stdin-tags < obsolete-tags.txt
 ")]
 pub struct RemoveArgs {
     /// CQL expression to match pages
     pub cql_expression: String,
 
     /// Tags to remove
-    #[arg(required = true)]
+    #[arg(required_unless_present = "stdin_tags", conflicts_with = "stdin_tags")]
     pub tags: Vec<String>,
 
+    /// Read the tags to remove from stdin, one per line
+    #[arg(long, conflicts_with = "interactive")]
+    pub stdin_tags: bool,
+
     /// Confirm each action interactively
     #[arg(long)]
     pub interactive: bool,
@@ -41,19 +50,44 @@ pub struct RemoveArgs {
     #[arg(long, default_value = "q")]
     pub abort_key: String,
 
+    /// Ask once per space whether to change its pages before any is
+    /// changed; pages in declined spaces count as skipped. With
+    /// --interactive, pages in approved spaces are then confirmed one by one
+    #[arg(long)]
+    pub confirm_each_space: bool,
+
+    /// Skip pages you don't have permission to edit instead of failing on them
+    #[arg(long)]
+    pub skip_no_edit: bool,
+
+    /// After the run, re-read each changed page and print its labels, to
+    /// confirm the changes approved in an --interactive session landed
+    #[arg(long)]
+    pub review: bool,
+
+    /// After applying, wait this long (e.g. 15s) and report how many changed
+    /// pages already show their new labels in search
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub verify_after: Option<std::time::Duration>,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
+
     /// Use regex to match tags
     #[arg(long)]
     pub regex: bool,
 }
 
 pub fn run(
-    args: RemoveArgs,
+    mut args: RemoveArgs,
     client: &ConfluenceClient,
-    dry_run: bool,
-    show_progress: bool,
-    format: ctag::models::OutputFormat,
-) -> Result<()> {
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
     let verbose = format.is_verbose();
+    if args.stdin_tags {
+        args.tags = crate::commands::read_stdin_tags()?;
+    }
 
     let compiled_regexes = if args.regex {
         let mut res = Vec::new();
@@ -72,40 +106,66 @@ pub fn run(
         ui::print_header("REMOVE TAGS");
     }
 
+    let page_filter = args.query.page_filter()?;
+
     // Get matching pages
-    let pages = crate::commands::get_matching_pages(
+    let started = Instant::now();
+    let pages = args.query.find_pages(
         client,
         &args.cql_expression,
-        100,
+        ctag::api::SEARCH_EXPAND,
+        format,
+        opts.show_progress,
+    )?;
+    let (pages, excluded) = crate::commands::exclude_pages(
+        client,
+        pages,
+        args.query.cql_exclude.as_deref(),
         format,
-        show_progress,
+        opts.show_progress,
     )?;
+    opts.timing.record("search", started);
+    let (pages, filtered) = page_filter.apply(pages);
+    let filtered = filtered + excluded;
+    let (pages, not_editable) =
+        crate::commands::retain_editable(client, pages, args.skip_no_edit, opts.read_concurrency);
 
     if pages.is_empty() {
-        ui::print_warning("No pages found matching the CQL expression.");
-        if dry_run {
-            ui::print_dry_run("No changes will be made.");
-        }
-        return Ok(());
+        return Ok(opts.no_pages_found());
     }
 
     if verbose {
         ui::print_info(&format!("Found {} matching pages.", pages.len()));
+        if filtered > 0 {
+            ui::print_info(&format!(
+                "Skipped {} pages filtered out by --title-match, --ancestor or --cql-exclude.",
+                filtered
+            ));
+        }
     }
 
-    if dry_run {
+    if opts.dry_run {
         ui::print_dry_run("No changes will be made.");
-        for page in &pages {
+        let mut shown = 0;
+        let mut unchanged = 0;
+        for (i, page) in pages.iter().enumerate() {
+            if opts.preview_exhausted(shown) {
+                ui::print_preview_remainder(pages.len() - i);
+                break;
+            }
             let page_id = match page.page_id() {
                 Some(id) => id,
-                None => continue,
+                None => {
+                    unchanged += 1;
+                    continue;
+                }
             };
 
             let title = page.title.as_deref().unwrap_or("Unknown");
             let space = page.space_name();
 
             let tags_to_remove = if let Some(regexes) = &compiled_regexes {
-                let current_tags = client.get_page_tags(page_id)?;
+                let current_tags = client.get_page_tags_cached(page_id)?;
                 ctag::api::filter_tags_by_regex(current_tags, regexes)
             } else {
                 args.tags.clone()
@@ -118,24 +178,38 @@ pub fn run(
                         sanitize_text(title)
                     ));
                 }
+                unchanged += 1;
                 continue;
             }
 
-            let display_title = page.printable_clickable_title(client.base_url());
+            let display_title = page.printable_clickable_title(&client.web_base_url());
             ui::print_page_action("Would remove tags from", &display_title, space);
             for tag in &tags_to_remove {
                 ui::print_substep(&format!("{}: {}", "Remove".red(), tag));
             }
+            shown += 1;
         }
-        return Ok(());
+        return Ok(ProcessResults::preview(pages.len(), unchanged));
     }
 
+    let approval = if args.confirm_each_space {
+        crate::commands::confirm_each_space(pages)
+    } else {
+        crate::commands::SpaceApproval {
+            pages,
+            declined: 0,
+            aborted: false,
+        }
+    };
+    let pages = approval.pages;
+
     // Process the pages
+    let started = Instant::now();
     let mut results = ProcessResults::new(pages.len());
 
     if args.interactive {
         // Interactive mode: sequential processing
-        let progress = if show_progress {
+        let progress = if opts.show_progress {
             Some(ui::create_progress_bar(pages.len() as u64))
         } else {
             None
@@ -153,7 +227,7 @@ pub fn run(
             let space = page.space_name();
 
             let tags_to_remove = if let Some(regexes) = &compiled_regexes {
-                let current_tags = client.get_page_tags(page_id)?;
+                let current_tags = client.get_page_tags_cached(page_id)?;
                 ctag::api::filter_tags_by_regex(current_tags, regexes)
             } else {
                 args.tags.clone()
@@ -167,7 +241,7 @@ pub fn run(
                 continue;
             }
 
-            let display_title = page.printable_clickable_title(client.base_url());
+            let display_title = page.printable_clickable_title(&client.web_base_url());
             if let Some(pb) = &progress {
                 pb.suspend(|| {
                     ui::print_page_action("Removing tags from", &display_title, space);
@@ -207,14 +281,23 @@ pub fn run(
                     break;
                 }
             }
-            let success = client.remove_tags(page_id, &tags_to_remove);
+            let tag_results = client.remove_tags(page_id, &tags_to_remove);
             results.processed += 1;
-            if success {
+            if TagResult::all_succeeded(&tag_results) {
                 results.success += 1;
                 results.tags_removed += tags_to_remove.len();
             } else {
                 results.failed += 1;
             }
+            results.details.push(ctag::models::ActionDetail {
+                page_id: page_id.to_string(),
+                title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+                space: space.to_string(),
+                url: display_title.clone(),
+                tags_added: vec![],
+                tags_removed: TagResult::succeeded(&tag_results),
+                tag_results,
+            });
             if let Some(pb) = &progress {
                 pb.inc(1);
             }
@@ -224,7 +307,7 @@ pub fn run(
         }
     } else {
         // Non-interactive mode: parallel processing
-        results = crate::commands::process_pages_parallel(&pages, show_progress, |page| {
+        results = crate::commands::process_pages_parallel(&pages, opts, |page| {
             let page_id = match page.page_id() {
                 Some(id) => id,
                 None => return crate::commands::ActionResult::Skipped,
@@ -240,25 +323,45 @@ pub fn run(
                 return crate::commands::ActionResult::Skipped;
             }
 
-            if client.remove_tags(page_id, &tags_to_remove) {
-                let detail = ctag::models::ActionDetail {
-                    page_id: page_id.to_string(),
-                    title: page.title.as_deref().unwrap_or("Unknown").to_string(),
-                    space: page.space_name().to_string(),
-                    url: page.printable_clickable_title(client.base_url()),
-                    tags_added: vec![],
-                    tags_removed: tags_to_remove.clone(),
-                };
+            let tag_results = client.remove_tags(page_id, &tags_to_remove);
+            let detail = ctag::models::ActionDetail {
+                page_id: page_id.to_string(),
+                title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+                space: page.space_name().to_string(),
+                url: page.printable_clickable_title(&client.web_base_url()),
+                tags_added: vec![],
+                tags_removed: TagResult::succeeded(&tag_results),
+                tag_results,
+            };
+            if TagResult::all_succeeded(&detail.tag_results) {
                 crate::commands::ActionResult::Success {
                     added: 0,
-                    removed: tags_to_remove.len(),
+                    removed: detail.tags_removed.len(),
                     detail: Some(detail),
                 }
             } else {
-                crate::commands::ActionResult::Failed
+                crate::commands::ActionResult::Failed {
+                    detail: Some(detail),
+                }
             }
         });
     }
-    ui::print_summary(&results, format);
-    Ok(())
+    opts.timing.record("apply", started);
+
+    // Pages removed by client-side filters or in declined spaces count as
+    // skipped
+    results.total += filtered + not_editable + approval.declined;
+    results.skipped += filtered + not_editable + approval.declined;
+    results.aborted |= approval.aborted;
+    results.record_pages(pages.iter().filter_map(|p| p.page_id()));
+
+    if args.review {
+        crate::commands::review_changes(client, &results.details, opts);
+    }
+    if let Some(delay) = args.verify_after {
+        crate::commands::report_search_visibility(client, &results.details, delay);
+    }
+
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
 }
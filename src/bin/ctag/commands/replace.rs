@@ -1,12 +1,15 @@
+use crate::commands::RunOptions;
 use crate::ui;
 use anyhow::Result;
 use clap::Args;
 use colored::Colorize;
-use ctag::api::ConfluenceClient;
+use ctag::api::{plan_replacements, ConfluenceClient, TagPlan};
 use ctag::models::sanitize_text;
 use ctag::models::ProcessResults;
 use dialoguer::Confirm;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
 #[derive(Args)]
 #[command(after_help = "\
@@ -23,6 +26,15 @@ EXAMPLES:
   # Interactive mode with confirmation
   ctag replace --interactive 'space = DOCS' 'draft=published'
 
+  # Swap two tags: pages with draft get final and vice versa
+  ctag replace --swap 'space = DOCS' 'draft=final'
+
+  # Rename draft to final, and add final to pages that have neither
+  ctag replace --add-if-missing 'space = DOCS' 'draft=final'
+
+  # Re-read tags afterwards to confirm each rename took effect
+  ctag replace --verify 'space = DOCS' 'confidential=internal'
+
   # Multiple replacements with regex
   ctag replace --regex 'label = migration' \\
     'v1-.*' 'legacy' \\
@@ -46,9 +58,47 @@ pub struct ReplaceArgs {
     #[arg(long, default_value = "q")]
     pub abort_key: String,
 
+    /// Ask once per space whether to change its pages before any is
+    /// changed; pages in declined spaces count as skipped. With
+    /// --interactive, pages in approved spaces are then confirmed one by one
+    #[arg(long)]
+    pub confirm_each_space: bool,
+
+    /// Skip pages you don't have permission to edit instead of failing on them
+    #[arg(long)]
+    pub skip_no_edit: bool,
+
+    /// After the run, re-read each changed page and print its labels, to
+    /// confirm the changes approved in an --interactive session landed
+    #[arg(long)]
+    pub review: bool,
+
+    /// After applying, wait this long (e.g. 15s) and report how many changed
+    /// pages already show their new labels in search
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub verify_after: Option<std::time::Duration>,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
+
     /// Use regex to match tags
     #[arg(long)]
     pub regex: bool,
+
+    /// Swap each pair both ways ('a=b' also turns b into a), planned against
+    /// each page's original tags
+    #[arg(long, conflicts_with = "regex")]
+    pub swap: bool,
+
+    /// On pages without the old tag, add the new tag instead of leaving the
+    /// page alone. These pages count as "added only" in the summary
+    #[arg(long, conflicts_with_all = ["regex", "swap"])]
+    pub add_if_missing: bool,
+
+    /// Re-read each page's tags after replacing and count the page as failed
+    /// unless the old tags are gone and the new ones present
+    #[arg(long)]
+    pub verify: bool,
 }
 
 /// Parse CLI tag pairs.
@@ -103,16 +153,24 @@ pub(crate) fn parse_tag_pairs(pairs: &[String], regex: bool) -> Result<HashMap<S
 pub fn run(
     args: ReplaceArgs,
     client: &ConfluenceClient,
-    dry_run: bool,
-    show_progress: bool,
-    format: ctag::models::OutputFormat,
-) -> Result<()> {
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
     let verbose = format.is_verbose();
     if verbose {
         ui::print_header("REPLACE TAGS");
     }
     // Parse tag pairs
-    let tag_mapping = parse_tag_pairs(&args.tag_pairs, args.regex)?;
+    let mut tag_mapping = parse_tag_pairs(&args.tag_pairs, args.regex)?;
+    if !args.regex {
+        tag_mapping = tag_mapping
+            .into_iter()
+            .map(|(old, new)| (opts.normalize_tag(&old), opts.normalize_tag(&new)))
+            .collect();
+    }
+    if args.swap {
+        tag_mapping = swap_mapping(tag_mapping)?;
+    }
     let compiled_regexes = if args.regex {
         let mut res = Vec::new();
         for (old, new) in &tag_mapping {
@@ -127,37 +185,80 @@ pub fn run(
         None
     };
 
+    let page_filter = args.query.page_filter()?;
+
     // Get matching pages
-    let pages = crate::commands::get_matching_pages(
+    let started = Instant::now();
+    let pages = args.query.find_pages(
         client,
         &args.cql_expression,
-        100,
+        ctag::api::SEARCH_EXPAND,
+        format,
+        opts.show_progress,
+    )?;
+    let (pages, excluded) = crate::commands::exclude_pages(
+        client,
+        pages,
+        args.query.cql_exclude.as_deref(),
         format,
-        show_progress,
+        opts.show_progress,
     )?;
+    opts.timing.record("search", started);
+    let (pages, filtered) = page_filter.apply(pages);
+    let filtered = filtered + excluded;
+    let (pages, not_editable) =
+        crate::commands::retain_editable(client, pages, args.skip_no_edit, opts.read_concurrency);
 
     if pages.is_empty() {
-        ui::print_warning("No pages found matching the CQL expression.");
-        if dry_run {
-            ui::print_dry_run("No changes will be made.");
-        }
-        return Ok(());
+        return Ok(opts.no_pages_found());
     }
     if verbose {
         ui::print_info(&format!("Found {} matching pages.", pages.len()));
+        if filtered > 0 {
+            ui::print_info(&format!(
+                "Skipped {} pages filtered out by --title-match, --ancestor or --cql-exclude.",
+                filtered
+            ));
+        }
     }
-    if dry_run {
+    if opts.dry_run {
         ui::print_dry_run("No changes will be made.");
-        for page in &pages {
+        let mut shown = 0;
+        let mut unchanged = 0;
+        for (i, page) in pages.iter().enumerate() {
+            if opts.preview_exhausted(shown) {
+                ui::print_preview_remainder(pages.len() - i);
+                break;
+            }
             let page_id = match page.page_id() {
                 Some(id) => id,
-                None => continue,
+                None => {
+                    unchanged += 1;
+                    continue;
+                }
             };
             let title = page.title.as_deref().unwrap_or("Unknown");
             let space = page.space_name();
+            if args.add_if_missing {
+                let plan = plan_add_if_missing(&client.get_page_tags(page_id)?, &tag_mapping);
+                if plan.is_empty() {
+                    unchanged += 1;
+                    continue;
+                }
+                let display_title = page.printable_clickable_title(&client.web_base_url());
+                ui::print_page_action("Would update tags on", &display_title, space);
+                for tag in &plan.remove {
+                    ui::print_substep(&format!("{}: {}", "Remove".red(), tag));
+                }
+                for tag in &plan.add {
+                    ui::print_substep(&format!("{}: {}", "Add".green(), tag));
+                }
+                shown += 1;
+                continue;
+            }
             let replacements = if let Some(regex_pairs) = &compiled_regexes {
-                let current_tags = client.get_page_tags(page_id)?;
-                ctag::api::compute_replacements_by_regex(current_tags, regex_pairs)
+                let current_tags = client.get_page_tags_cached(page_id)?;
+                regex_replacements(current_tags, regex_pairs, |tag| opts.normalize_tag(tag))
             } else {
                 tag_mapping.clone()
             };
@@ -169,10 +270,11 @@ pub fn run(
                         sanitize_text(title)
                     ));
                 }
+                unchanged += 1;
                 continue;
             }
 
-            let display_title = page.printable_clickable_title(client.base_url());
+            let display_title = page.printable_clickable_title(&client.web_base_url());
             ui::print_page_action("Would replace tags on", &display_title, space);
             for (old, new) in &replacements {
                 ui::print_substep(&format!(
@@ -183,14 +285,35 @@ pub fn run(
                     new.green()
                 ));
             }
+            shown += 1;
         }
-        return Ok(());
+        return Ok(ProcessResults::preview(pages.len(), unchanged));
     }
+    let mode = if args.add_if_missing {
+        ReplaceMode::AddIfMissing
+    } else if args.swap {
+        ReplaceMode::Swap
+    } else {
+        ReplaceMode::Rename
+    };
+
+    let approval = if args.confirm_each_space {
+        crate::commands::confirm_each_space(pages)
+    } else {
+        crate::commands::SpaceApproval {
+            pages,
+            declined: 0,
+            aborted: false,
+        }
+    };
+    let pages = approval.pages;
+
     // Process the pages
+    let started = Instant::now();
     let mut results = ProcessResults::new(pages.len());
     if args.interactive {
         // Interactive mode: sequential processing
-        let progress = if show_progress {
+        let progress = if opts.show_progress {
             Some(ui::create_progress_bar(pages.len() as u64))
         } else {
             None
@@ -206,8 +329,8 @@ pub fn run(
 
             let space = page.space_name();
             let replacements = if let Some(regex_pairs) = &compiled_regexes {
-                let current_tags = client.get_page_tags(page_id)?;
-                ctag::api::compute_replacements_by_regex(current_tags, regex_pairs)
+                let current_tags = client.get_page_tags_cached(page_id)?;
+                regex_replacements(current_tags, regex_pairs, |tag| opts.normalize_tag(tag))
             } else {
                 tag_mapping.clone()
             };
@@ -218,7 +341,7 @@ pub fn run(
                 }
                 continue;
             }
-            let display_title = page.printable_clickable_title(client.base_url());
+            let display_title = page.printable_clickable_title(&client.web_base_url());
             if let Some(pb) = &progress {
                 pb.suspend(|| {
                     ui::print_page_action("Replacing tags on", &display_title, space);
@@ -269,15 +392,30 @@ pub fn run(
                     break;
                 }
             }
-            let success = client.replace_tags(page_id, &replacements);
             results.processed += 1;
-            if success {
-                results.success += 1;
-                use std::collections::HashSet;
-                results.tags_removed += replacements.len();
-                results.tags_added += replacements.values().collect::<HashSet<_>>().len();
-            } else {
-                results.failed += 1;
+            match apply_replacements(client, page_id, &replacements, mode) {
+                Some((removed, added)) => {
+                    if args.verify && !verify_replacement(client, page_id, &removed, &added) {
+                        results.failed += 1;
+                    } else {
+                        results.success += 1;
+                        if is_added_only(&removed, &added) {
+                            results.added_only += 1;
+                        }
+                        results.tags_removed += removed.len();
+                        results.tags_added += added.len();
+                    }
+                    results.details.push(ctag::models::ActionDetail {
+                        page_id: page_id.to_string(),
+                        title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+                        space: space.to_string(),
+                        url: display_title.clone(),
+                        tags_added: added,
+                        tags_removed: removed,
+                        tag_results: vec![],
+                    });
+                }
+                None => results.failed += 1,
             }
             if let Some(pb) = &progress {
                 pb.inc(1);
@@ -288,7 +426,8 @@ pub fn run(
         }
     } else {
         // Non-interactive mode: parallel processing
-        results = crate::commands::process_pages_parallel(&pages, show_progress, |page| {
+        let added_only = AtomicUsize::new(0);
+        results = crate::commands::process_pages_parallel(&pages, opts, |page| {
             let page_id = match page.page_id() {
                 Some(id) => id,
                 None => return crate::commands::ActionResult::Skipped,
@@ -296,7 +435,7 @@ pub fn run(
 
             let replacements = if let Some(regex_pairs) = &compiled_regexes {
                 let current_tags = client.get_page_tags(page_id).unwrap_or_default();
-                ctag::api::compute_replacements_by_regex(current_tags, regex_pairs)
+                regex_replacements(current_tags, regex_pairs, |tag| opts.normalize_tag(tag))
             } else {
                 tag_mapping.clone()
             };
@@ -305,39 +444,301 @@ pub fn run(
                 return crate::commands::ActionResult::Skipped;
             }
 
-            if client.replace_tags(page_id, &replacements) {
-                use std::collections::HashSet;
-                let removed_count = replacements.len();
-                let added_count = replacements.values().collect::<HashSet<_>>().len();
-
+            if let Some((removed, added)) = apply_replacements(client, page_id, &replacements, mode)
+            {
                 let detail = ctag::models::ActionDetail {
                     page_id: page_id.to_string(),
                     title: page.title.as_deref().unwrap_or("Unknown").to_string(),
                     space: page.space_name().to_string(),
-                    url: page.printable_clickable_title(client.base_url()), // This has escape codes but is what we have for now. Ideally plain URL.
-                    tags_added: replacements.values().cloned().collect(),
-                    tags_removed: replacements.keys().cloned().collect(),
+                    url: page.printable_clickable_title(&client.web_base_url()), // This has escape codes but is what we have for now. Ideally plain URL.
+                    tags_added: added.clone(),
+                    tags_removed: removed.clone(),
+                    tag_results: vec![],
                 };
+                if args.verify && !verify_replacement(client, page_id, &removed, &added) {
+                    return crate::commands::ActionResult::Failed {
+                        detail: Some(detail),
+                    };
+                }
+                if is_added_only(&removed, &added) {
+                    added_only.fetch_add(1, Ordering::Relaxed);
+                }
 
                 crate::commands::ActionResult::Success {
-                    added: added_count,
-                    removed: removed_count,
+                    added: added.len(),
+                    removed: removed.len(),
                     detail: Some(detail),
                 }
             } else {
-                crate::commands::ActionResult::Failed
+                crate::commands::ActionResult::Failed { detail: None }
             }
         });
+        results.added_only = added_only.into_inner();
+    }
+    opts.timing.record("apply", started);
+
+    // Pages removed by client-side filters or in declined spaces count as
+    // skipped
+    results.total += filtered + not_editable + approval.declined;
+    results.skipped += filtered + not_editable + approval.declined;
+    results.aborted |= approval.aborted;
+    results.record_pages(pages.iter().filter_map(|p| p.page_id()));
+
+    if args.review {
+        crate::commands::review_changes(client, &results.details, opts);
     }
+    if let Some(delay) = args.verify_after {
+        crate::commands::report_search_visibility(client, &results.details, delay);
+    }
+
     // Display results
-    ui::print_summary(&results, format);
-    Ok(())
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
+}
+
+/// Add the reverse of every pair so `a=b` also maps b to a
+fn swap_mapping(mapping: HashMap<String, String>) -> Result<HashMap<String, String>> {
+    let mut swapped = mapping.clone();
+    for (old, new) in mapping {
+        if old == new {
+            anyhow::bail!("Cannot swap '{}' with itself", old);
+        }
+        if let Some(existing) = swapped.insert(new.clone(), old.clone()) {
+            if existing != old {
+                anyhow::bail!(
+                    "Conflicting swap: '{}' would become both '{}' and '{}'",
+                    new,
+                    existing,
+                    old
+                );
+            }
+        }
+    }
+    Ok(swapped)
+}
+
+/// How `apply_replacements` treats each old=new pair
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReplaceMode {
+    /// Rename old to new on pages that have old
+    Rename,
+    /// Rename both ways, planned against the page's original tags
+    Swap,
+    /// Rename where old is present, otherwise add new (`--add-if-missing`)
+    AddIfMissing,
+}
+
+/// Plan for `--add-if-missing`: rename the old tags the page has and add the
+/// new tag of every pair where the page has neither
+fn plan_add_if_missing(current: &[String], mapping: &HashMap<String, String>) -> TagPlan {
+    let mut plan = plan_replacements(current, mapping);
+    for (old, new) in mapping {
+        if !current.contains(old) && !current.contains(new) && !plan.add.contains(new) {
+            plan.add.push(new.clone());
+        }
+    }
+    plan
+}
+
+/// The page gained tags without losing any: an `--add-if-missing` addition
+/// rather than a rename
+fn is_added_only(removed: &[String], added: &[String]) -> bool {
+    removed.is_empty() && !added.is_empty()
+}
+
+/// Apply replacements to one page, returning the (removed, added) tags or
+/// `None` on failure. Swaps and additions are planned against the page's
+/// current tags so no replacement sees the result of another.
+fn apply_replacements(
+    client: &ConfluenceClient,
+    page_id: &str,
+    replacements: &HashMap<String, String>,
+    mode: ReplaceMode,
+) -> Option<(Vec<String>, Vec<String>)> {
+    if mode != ReplaceMode::Rename {
+        let current = match client.get_page_tags(page_id) {
+            Ok(tags) => tags,
+            Err(e) => {
+                log::error!("Failed to get current tags for page {}: {}", page_id, e);
+                return None;
+            }
+        };
+        let plan = match mode {
+            ReplaceMode::AddIfMissing => plan_add_if_missing(&current, replacements),
+            _ => plan_replacements(&current, replacements),
+        };
+        return client
+            .apply_tag_plan(page_id, &plan)
+            .then_some((plan.remove, plan.add));
+    }
+    let applied = client.replace_tags(page_id, replacements)?;
+    Some(rename_changes(&applied))
+}
+
+/// The (removed, added) tags of the renames applied to a page
+fn rename_changes(applied: &[(String, String)]) -> (Vec<String>, Vec<String>) {
+    let mut added: Vec<String> = Vec::new();
+    for (_, new) in applied {
+        if !added.contains(new) {
+            added.push(new.clone());
+        }
+    }
+    (applied.iter().map(|(old, _)| old.clone()).collect(), added)
+}
+
+/// Re-read the page's tags and warn unless every removed tag is gone and
+/// every added tag is present. A tag both removed and added (a swap) only
+/// needs to be present.
+fn verify_replacement(
+    client: &ConfluenceClient,
+    page_id: &str,
+    removed: &[String],
+    added: &[String],
+) -> bool {
+    let current = match client.get_page_tags(page_id) {
+        Ok(tags) => tags,
+        Err(e) => {
+            ui::defer_warning(format!("Could not verify page {}: {:#}", page_id, e));
+            return false;
+        }
+    };
+    let mismatches = replacement_mismatches(&current, removed, added);
+    if !mismatches.is_empty() {
+        ui::defer_warning(format!(
+            "Verification failed on page {}: {}",
+            page_id,
+            mismatches.join(", ")
+        ));
+    }
+    mismatches.is_empty()
+}
+
+/// Regex replacements for a page's current tags. The new tags are
+/// normalized like literal ones, since a capture replacement can produce
+/// upper case that Confluence then stores lowercase.
+fn regex_replacements(
+    current: Vec<String>,
+    regex_pairs: &[(regex::Regex, String)],
+    normalize: impl Fn(&str) -> String,
+) -> HashMap<String, String> {
+    ctag::api::compute_replacements_by_regex(current, regex_pairs)
+        .into_iter()
+        .map(|(old, new)| (old, normalize(&new)))
+        .collect()
+}
+
+/// Describe how `current` differs from the expected result. Tags are
+/// compared exactly; requested tags are already normalized to the stored form.
+fn replacement_mismatches(current: &[String], removed: &[String], added: &[String]) -> Vec<String> {
+    let has = |tag: &str| current.iter().any(|t| t == tag);
+    let mut mismatches = Vec::new();
+    for tag in removed {
+        if !added.contains(tag) && has(tag) {
+            mismatches.push(format!("'{}' still present", tag));
+        }
+    }
+    for tag in added {
+        if !has(tag) {
+            mismatches.push(format!("'{}' missing", tag));
+        }
+    }
+    mismatches
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn replacement_mismatches_reports_leftovers_and_missing() {
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert!(
+            replacement_mismatches(&tags(&["new"]), &tags(&["old"]), &tags(&["new"])).is_empty()
+        );
+        // Case differences are mismatches; callers normalize requested tags first
+        assert_eq!(
+            replacement_mismatches(&tags(&["new"]), &tags(&["old"]), &tags(&["New"])),
+            vec!["'New' missing"]
+        );
+        // Swapped tags only need to be present
+        assert!(
+            replacement_mismatches(&tags(&["a", "b"]), &tags(&["a", "b"]), &tags(&["b", "a"]))
+                .is_empty()
+        );
+        assert_eq!(
+            replacement_mismatches(&tags(&["old"]), &tags(&["old"]), &tags(&["new"])),
+            vec!["'old' still present", "'new' missing"]
+        );
+    }
+
+    #[test]
+    fn renames_only_report_pairs_found_on_the_page() {
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let mapping = parse_tag_pairs(&["a=x".to_string(), "b=y".to_string()], false).unwrap();
+        // The page has `a` but not `b`
+        let current = tags(&["a", "keep"]);
+        let applied: Vec<(String, String)> = ctag::api::renames_on_page(&current, &mapping)
+            .into_iter()
+            .map(|(old, new)| (old.clone(), new.clone()))
+            .collect();
+        let (removed, added) = rename_changes(&applied);
+        assert_eq!((removed, added), (tags(&["a"]), tags(&["x"])));
+        // So --verify checks only `x`, which the rename put on the page
+        assert!(
+            replacement_mismatches(&tags(&["x", "keep"]), &tags(&["a"]), &tags(&["x"])).is_empty()
+        );
+    }
+
+    #[test]
+    fn regex_replacements_normalize_new_tags() {
+        let pairs = vec![(
+            regex::Regex::new(r"^v(\d+)-(.*)$").unwrap(),
+            "$2-V$1".to_string(),
+        )];
+        let current = vec!["v2-api".to_string()];
+        let mapping = regex_replacements(current.clone(), &pairs, str::to_lowercase);
+        assert_eq!(mapping["v2-api"], "api-v2");
+        // Verified against the stored, lowercase label
+        assert!(replacement_mismatches(
+            &["api-v2".to_string()],
+            &["v2-api".to_string()],
+            &[mapping["v2-api"].clone()]
+        )
+        .is_empty());
+        let exact = regex_replacements(current, &pairs, str::to_string);
+        assert_eq!(exact["v2-api"], "api-V2");
+    }
+
+    #[test]
+    fn plan_add_if_missing_renames_or_adds() {
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let mapping = parse_tag_pairs(&["draft=final".to_string()], false).unwrap();
+
+        let renamed = plan_add_if_missing(&tags(&["draft", "x"]), &mapping);
+        assert_eq!(
+            (renamed.remove, renamed.add),
+            (tags(&["draft"]), tags(&["final"]))
+        );
+
+        let added = plan_add_if_missing(&tags(&["x"]), &mapping);
+        assert!(added.remove.is_empty());
+        assert_eq!(added.add, tags(&["final"]));
+        assert!(is_added_only(&added.remove, &added.add));
+
+        assert!(plan_add_if_missing(&tags(&["final"]), &mapping).is_empty());
+    }
+
+    #[test]
+    fn swap_mapping_adds_reverse_pairs() {
+        let mapping = parse_tag_pairs(&["draft=final".to_string()], false).unwrap();
+        let swapped = swap_mapping(mapping).unwrap();
+        assert_eq!(swapped.get("draft").map(String::as_str), Some("final"));
+        assert_eq!(swapped.get("final").map(String::as_str), Some("draft"));
+
+        let conflicting = parse_tag_pairs(&["a=b".to_string(), "c=b".to_string()], false).unwrap();
+        assert!(swap_mapping(conflicting).is_err());
+    }
+
     #[test]
     fn parse_tag_pairs_trims_whitespace_and_parses_correctly() {
         let input = vec!["old=new".to_string(), " foo = bar ".to_string()];
@@ -0,0 +1,437 @@
+use crate::commands::RunOptions;
+use crate::ui;
+use anyhow::Result;
+use clap::Args;
+use comfy_table::modifiers::UTF8_ROUND_CORNERS;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Attribute, Cell, CellAlignment, Color, Table};
+use ctag::aliases::TagAliases;
+use ctag::api::ConfluenceClient;
+use ctag::models::{OutputFormat, ProcessResults, SearchResultItem};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::AtomicUsize;
+use std::time::Instant;
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # How many pages carry each tag in a space
+  ctag stats 'space = DOCS'
+
+  # The 20 most common tags as CSV
+  ctag stats 'space = DOCS' --top 20 --format csv
+
+  # Also list the spaces from a file that matched no pages
+  ctag stats 'type = page' --spaces-file spaces.txt
+")]
+pub struct StatsArgs {
+    /// CQL expression to match pages
+    pub cql_expression: String,
+
+    /// Only show the N most common tags
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+
+    /// Count aliased labels under their canonical name, using a TOML file of
+    /// `canonical = ["alias", ...]` entries
+    #[arg(long, value_name = "FILE")]
+    pub alias_file: Option<String>,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
+}
+
+/// A tag and the number of pages carrying it
+#[derive(Debug, Serialize, PartialEq)]
+struct TagCount {
+    tag: String,
+    pages: usize,
+}
+
+/// Why a space given with `--space` or `--spaces-file` matched no pages
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum SpaceStatus {
+    /// Readable, but no page in it matched the query
+    Empty,
+    /// Missing, or the user can't read it; Confluence doesn't tell them apart
+    NoAccess,
+    /// The probe itself failed
+    Unknown,
+}
+
+impl SpaceStatus {
+    fn describe(self) -> &'static str {
+        match self {
+            SpaceStatus::Empty => "empty",
+            SpaceStatus::NoAccess => "no access",
+            SpaceStatus::Unknown => "unknown (probe failed)",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct UnmatchedSpace {
+    space: String,
+    status: SpaceStatus,
+}
+
+/// Tag counts, plus the requested spaces that matched nothing when the
+/// search was limited to a list of spaces
+#[derive(Serialize)]
+struct StatsReport<'a> {
+    tags: &'a [TagCount],
+    unmatched_spaces: Option<&'a [UnmatchedSpace]>,
+}
+
+/// JSON output: the bare list of tag counts, or the whole report when the
+/// search was limited to a list of spaces
+fn stats_json(report: &StatsReport) -> serde_json::Result<serde_json::Value> {
+    match report.unmatched_spaces {
+        Some(_) => serde_json::to_value(report),
+        None => serde_json::to_value(report.tags),
+    }
+}
+
+/// Requested space keys that none of `pages` belongs to, in request order.
+/// Space keys are compared ignoring ASCII case, as CQL does.
+fn unmatched_space_keys<'a>(requested: &'a [String], pages: &[SearchResultItem]) -> Vec<&'a str> {
+    let found: HashSet<String> = pages
+        .iter()
+        .filter_map(|page| page.space_key())
+        .map(|key| key.to_ascii_uppercase())
+        .collect();
+    requested
+        .iter()
+        .filter(|key| !found.contains(&key.to_ascii_uppercase()))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Look up each space to tell empty spaces from ones the user can't read
+fn probe_spaces(client: &ConfluenceClient, keys: &[&str]) -> Vec<UnmatchedSpace> {
+    keys.par_iter()
+        .map(|key| {
+            let status = match client.can_read_space(key) {
+                Ok(true) => SpaceStatus::Empty,
+                Ok(false) => SpaceStatus::NoAccess,
+                Err(e) => {
+                    ui::defer_warning(format!("Could not look up space {}: {:#}", key, e));
+                    SpaceStatus::Unknown
+                }
+            };
+            UnmatchedSpace {
+                space: key.to_string(),
+                status,
+            }
+        })
+        .collect()
+}
+
+/// Pages per tag, most common first and ties by name. A page counts once
+/// per tag even when several of its labels fold into the same canonical name.
+fn tag_frequency<'a>(
+    pages: impl IntoIterator<Item = &'a [String]>,
+    aliases: Option<&TagAliases>,
+) -> Vec<TagCount> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for tags in pages {
+        let distinct: HashSet<&str> = tags
+            .iter()
+            .map(|tag| aliases.map_or(tag.as_str(), |a| a.canonical(tag)))
+            .collect();
+        for tag in distinct {
+            *counts.entry(tag.to_string()).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, pages)| TagCount { tag, pages })
+        .collect();
+    counts.sort_by(|a, b| b.pages.cmp(&a.pages).then_with(|| a.tag.cmp(&b.tag)));
+    counts
+}
+
+pub fn run(
+    args: StatsArgs,
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
+    let verbose = format.is_verbose();
+    if verbose {
+        ui::print_header("TAG STATS");
+    }
+    let page_filter = args.query.page_filter()?;
+    let aliases = args
+        .alias_file
+        .as_deref()
+        .map(TagAliases::from_file)
+        .transpose()?;
+    let requested_spaces = args.query.space_keys()?;
+
+    let started = Instant::now();
+    let pages = args.query.find_pages(
+        client,
+        &args.cql_expression,
+        ctag::api::SEARCH_EXPAND,
+        format,
+        opts.show_progress,
+    )?;
+    // Judged on the search results, before exclusions and client-side
+    // filters narrow them
+    let unmatched = if requested_spaces.is_empty() {
+        None
+    } else {
+        let keys = unmatched_space_keys(&requested_spaces, &pages);
+        Some(crate::commands::in_pool(opts.read_concurrency, || {
+            probe_spaces(client, &keys)
+        }))
+    };
+    let (pages, _) = crate::commands::exclude_pages(
+        client,
+        pages,
+        args.query.cql_exclude.as_deref(),
+        format,
+        opts.show_progress,
+    )?;
+    opts.timing.record("search", started);
+    let (pages, _) = page_filter.apply(pages);
+
+    if pages.is_empty() {
+        let report = StatsReport {
+            tags: &[],
+            unmatched_spaces: unmatched.as_deref(),
+        };
+        if format.is_structured() {
+            print_stats(&report, 0, 0, format, &opts.csv)?;
+        } else {
+            opts.print_empty_message();
+            print_unmatched_spaces(report.unmatched_spaces);
+        }
+        return Ok(ProcessResults::new(0));
+    }
+    if verbose {
+        ui::print_info(&format!("Found {} matching pages.", pages.len()));
+    }
+
+    let progress = if opts.show_progress && !format.is_structured() {
+        Some(ui::create_progress_bar(pages.len() as u64))
+    } else {
+        None
+    };
+    let started = Instant::now();
+    let page_data = crate::commands::in_pool(opts.read_concurrency, || {
+        crate::commands::get::collect_page_data(
+            &pages,
+            client,
+            progress.as_ref(),
+            &AtomicUsize::new(0),
+            false,
+            false,
+        )
+    });
+    opts.timing.record("labels", started);
+    if let Some(p) = &progress {
+        p.finish_and_clear();
+    }
+
+    let counts = tag_frequency(
+        page_data.iter().map(|p| p.tags.as_slice()),
+        aliases.as_ref(),
+    );
+    let shown = args.top.map_or(counts.len(), |n| n.min(counts.len()));
+    let report = StatsReport {
+        tags: &counts[..shown],
+        unmatched_spaces: unmatched.as_deref(),
+    };
+    print_stats(&report, counts.len(), page_data.len(), format, &opts.csv)?;
+
+    let mut results = ProcessResults::new(pages.len());
+    results.processed = page_data.len();
+    results.success = page_data.len();
+    results.skipped = pages.len() - page_data.len();
+    Ok(results)
+}
+
+/// Print the report's tag counts, the first of `distinct` tags found on
+/// `page_count` pages, and the spaces that matched nothing
+fn print_stats(
+    report: &StatsReport,
+    distinct: usize,
+    page_count: usize,
+    format: OutputFormat,
+    csv_options: &ui::CsvOptions,
+) -> Result<()> {
+    let counts = report.tags;
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats_json(report)?)?),
+        OutputFormat::Csv => {
+            let mut wtr = csv_options.writer(std::io::stdout())?;
+            wtr.write_record(["tag", "pages"])?;
+            for count in counts {
+                wtr.write_record([count.tag.as_str(), &count.pages.to_string()])?;
+            }
+            wtr.flush()?;
+            // The CSV holds one table, so the spaces go to stderr
+            for space in report.unmatched_spaces.unwrap_or_default() {
+                ui::print_warning(&format!(
+                    "Space {} matched no pages ({})",
+                    space.space,
+                    space.status.describe()
+                ));
+            }
+        }
+        OutputFormat::Simple | OutputFormat::Verbose => {
+            if counts.is_empty() {
+                println!("No tags found.");
+                print_unmatched_spaces(report.unmatched_spaces);
+                return Ok(());
+            }
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec![
+                    Cell::new("Tag")
+                        .add_attribute(Attribute::Bold)
+                        .fg(Color::Cyan),
+                    Cell::new("Pages")
+                        .add_attribute(Attribute::Bold)
+                        .fg(Color::Cyan),
+                ]);
+            for count in counts {
+                table.add_row(vec![
+                    Cell::new(&count.tag),
+                    Cell::new(count.pages).set_alignment(CellAlignment::Right),
+                ]);
+            }
+            println!("{}", table);
+            let mut summary = format!("{} pages, {} distinct tags", page_count, distinct);
+            if counts.len() < distinct {
+                summary.push_str(&format!(", showing the top {}", counts.len()));
+            }
+            ui::print_info(&summary);
+            print_unmatched_spaces(report.unmatched_spaces);
+        }
+    }
+    Ok(())
+}
+
+/// Table of the requested spaces that matched no pages, if there are any
+fn print_unmatched_spaces(spaces: Option<&[UnmatchedSpace]>) {
+    let Some(spaces) = spaces.filter(|s| !s.is_empty()) else {
+        return;
+    };
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("Space without matches")
+                .add_attribute(Attribute::Bold)
+                .fg(Color::Cyan),
+            Cell::new("Status")
+                .add_attribute(Attribute::Bold)
+                .fg(Color::Cyan),
+        ]);
+    for space in spaces {
+        let status = Cell::new(space.status.describe());
+        let status = match space.status {
+            SpaceStatus::Empty => status,
+            SpaceStatus::NoAccess | SpaceStatus::Unknown => status.fg(Color::Yellow),
+        };
+        table.add_row(vec![Cell::new(&space.space), status]);
+    }
+    println!("{}", table);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn frequency_counts_pages_per_tag_most_common_first() {
+        let pages = [
+            tags(&["api", "draft"]),
+            tags(&["api", "howto"]),
+            tags(&["draft", "api", "draft"]),
+            tags(&["rfc"]),
+        ];
+        let counts = tag_frequency(pages.iter().map(Vec::as_slice), None);
+        let flat: Vec<(&str, usize)> = counts.iter().map(|c| (c.tag.as_str(), c.pages)).collect();
+        assert_eq!(
+            flat,
+            vec![("api", 3), ("draft", 2), ("howto", 1), ("rfc", 1)]
+        );
+
+        let json = serde_json::to_value(&counts[..1]).unwrap();
+        assert_eq!(json, serde_json::json!([{"tag": "api", "pages": 3}]));
+
+        // A page carrying both an alias and its canonical name counts once
+        let aliases = TagAliases::parse(r#"api = ["howto", "rfc"]"#).unwrap();
+        let counts = tag_frequency(pages.iter().map(Vec::as_slice), Some(&aliases));
+        assert_eq!(
+            counts[0],
+            TagCount {
+                tag: "api".to_string(),
+                pages: 4
+            }
+        );
+        assert_eq!(counts.len(), 2);
+    }
+
+    #[test]
+    fn unmatched_spaces_are_listed_in_request_order() {
+        let page = |key: &str| -> SearchResultItem {
+            serde_json::from_value(serde_json::json!({
+                "content": {"id": "1", "type": "page", "space": {"key": key}},
+                "title": "Page"
+            }))
+            .unwrap()
+        };
+        let requested = tags(&["DOCS", "OLD", "eng", "SECRET"]);
+        let pages = [page("DOCS"), page("ENG")];
+        assert_eq!(
+            unmatched_space_keys(&requested, &pages),
+            vec!["OLD", "SECRET"]
+        );
+
+        let unmatched = [
+            UnmatchedSpace {
+                space: "OLD".to_string(),
+                status: SpaceStatus::Empty,
+            },
+            UnmatchedSpace {
+                space: "SECRET".to_string(),
+                status: SpaceStatus::NoAccess,
+            },
+        ];
+        let report = StatsReport {
+            tags: &[],
+            unmatched_spaces: Some(&unmatched),
+        };
+        assert_eq!(
+            stats_json(&report).unwrap(),
+            serde_json::json!({
+                "tags": [],
+                "unmatched_spaces": [
+                    {"space": "OLD", "status": "empty"},
+                    {"space": "SECRET", "status": "no_access"}
+                ]
+            })
+        );
+        let without_spaces = StatsReport {
+            tags: &[],
+            unmatched_spaces: None,
+        };
+        // Without a space list the output stays a bare list
+        assert_eq!(stats_json(&without_spaces).unwrap(), serde_json::json!([]));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::commands::{ActionResult, RunOptions};
+use crate::ui;
+use anyhow::Result;
+use clap::Args;
+use colored::Colorize;
+use ctag::api::ConfluenceClient;
+use ctag::models::{ActionDetail, ProcessResults};
+use std::time::Instant;
+
+/// Tag used when `--tag` is not given; added and removed straight away
+pub const DEFAULT_TOUCH_TAG: &str = "ctag-touch";
+
+#[derive(Args)]
+#[command(after_help = "\
+EXAMPLES:
+  # Nudge Confluence to reindex every page in a space
+  ctag touch 'space = DOCS'
+
+  # Remove and re-add an existing label on the pages that have it
+  ctag touch 'label = marker' --tag marker
+
+  # See which pages would be touched
+  ctag --dry-run touch 'space = DOCS'
+")]
+pub struct TouchArgs {
+    /// CQL expression to match pages
+    pub cql_expression: String,
+
+    /// Label to cycle. Pages that have it get it removed and re-added; other
+    /// pages get it added and removed again
+    #[arg(long, default_value = DEFAULT_TOUCH_TAG)]
+    pub tag: String,
+
+    /// Skip pages you don't have permission to edit instead of failing on them
+    #[arg(long)]
+    pub skip_no_edit: bool,
+
+    #[command(flatten)]
+    pub query: crate::commands::QueryArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TouchStep {
+    Add,
+    Remove,
+}
+
+/// Order of the two label writes so the page ends with its original labels
+fn touch_steps(current: &[String], tag: &str) -> [TouchStep; 2] {
+    if current.iter().any(|t| t == tag) {
+        [TouchStep::Remove, TouchStep::Add]
+    } else {
+        [TouchStep::Add, TouchStep::Remove]
+    }
+}
+
+pub fn run(
+    args: TouchArgs,
+    client: &ConfluenceClient,
+    opts: &RunOptions,
+) -> Result<ProcessResults> {
+    let format = opts.format;
+    let verbose = format.is_verbose();
+    if verbose {
+        ui::print_header("TOUCH PAGES");
+    }
+    let tag = opts.normalize_tag(&args.tag);
+    let page_filter = args.query.page_filter()?;
+
+    let started = Instant::now();
+    let pages = args.query.find_pages(
+        client,
+        &args.cql_expression,
+        ctag::api::SEARCH_EXPAND,
+        format,
+        opts.show_progress,
+    )?;
+    let (pages, excluded) = crate::commands::exclude_pages(
+        client,
+        pages,
+        args.query.cql_exclude.as_deref(),
+        format,
+        opts.show_progress,
+    )?;
+    opts.timing.record("search", started);
+    let (pages, filtered) = page_filter.apply(pages);
+    let filtered = filtered + excluded;
+    let (pages, not_editable) =
+        crate::commands::retain_editable(client, pages, args.skip_no_edit, opts.read_concurrency);
+
+    if pages.is_empty() {
+        return Ok(opts.no_pages_found());
+    }
+    if verbose {
+        ui::print_info(&format!("Found {} matching pages.", pages.len()));
+        if filtered > 0 {
+            ui::print_info(&format!(
+                "Skipped {} pages filtered out by --title-match, --ancestor or --cql-exclude.",
+                filtered
+            ));
+        }
+    }
+
+    if opts.dry_run {
+        ui::print_dry_run("No changes will be made.");
+        for (i, page) in pages.iter().enumerate() {
+            if opts.preview_exhausted(i) {
+                ui::print_preview_remainder(pages.len() - i);
+                break;
+            }
+            let display_title = page.printable_clickable_title(&client.web_base_url());
+            ui::print_page_action("Would touch", &display_title, page.space_name());
+            ui::print_substep(&format!("{}: {}", "Cycle".yellow(), tag));
+        }
+        return Ok(ProcessResults::new(pages.len()));
+    }
+
+    let started = Instant::now();
+    let mut results = crate::commands::process_pages_parallel(&pages, opts, |page| {
+        let page_id = match page.page_id() {
+            Some(id) => id,
+            None => return ActionResult::Skipped,
+        };
+        let detail = ActionDetail {
+            page_id: page_id.to_string(),
+            title: page.title.as_deref().unwrap_or("Unknown").to_string(),
+            space: page.space_name().to_string(),
+            url: page.printable_clickable_title(&client.web_base_url()),
+            tags_added: Vec::new(),
+            tags_removed: Vec::new(),
+            tag_results: Vec::new(),
+        };
+        let current = match client.get_page_tags(page_id) {
+            Ok(tags) => tags,
+            Err(_) => {
+                return ActionResult::Failed {
+                    detail: Some(detail),
+                }
+            }
+        };
+        let steps = touch_steps(&current, &tag);
+        for (i, step) in steps.iter().enumerate() {
+            let outcome = match step {
+                TouchStep::Add => client.add_tag(page_id, &tag),
+                TouchStep::Remove => client.remove_tag(page_id, &tag),
+            };
+            if let Err(e) = outcome {
+                // A failed second write leaves the page with different labels
+                if i == 1 {
+                    ui::defer_warning(format!(
+                        "Page {} was left {} '{}': {}",
+                        page_id,
+                        if *step == TouchStep::Add {
+                            "without"
+                        } else {
+                            "with"
+                        },
+                        tag,
+                        e
+                    ));
+                }
+                return ActionResult::Failed {
+                    detail: Some(detail),
+                };
+            }
+        }
+        ActionResult::Success {
+            added: 0,
+            removed: 0,
+            detail: Some(detail),
+        }
+    });
+    opts.timing.record("apply", started);
+
+    // Pages removed by client-side filters count as skipped
+    results.total += filtered + not_editable;
+    results.skipped += filtered + not_editable;
+    results.record_pages(pages.iter().filter_map(|p| p.page_id()));
+
+    ui::print_summary(&results, format, &opts.csv);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touch_steps_restore_original_labels() {
+        let current = vec!["marker".to_string(), "other".to_string()];
+        assert_eq!(
+            touch_steps(&current, "marker"),
+            [TouchStep::Remove, TouchStep::Add]
+        );
+        assert_eq!(
+            touch_steps(&current, DEFAULT_TOUCH_TAG),
+            [TouchStep::Add, TouchStep::Remove]
+        );
+    }
+}
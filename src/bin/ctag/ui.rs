@@ -1,7 +1,32 @@
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Set by `--quiet`: informational messages are dropped, warnings and
+/// errors still print
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Set by `--no-summary`: the summary block after a bulk run is left out
+static NO_SUMMARY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_summary(no_summary: bool) {
+    NO_SUMMARY.store(no_summary, Ordering::Relaxed);
+}
 
 pub fn print_step(msg: &str) {
+    if quiet() {
+        return;
+    }
     eprintln!("{} {}", "•".bold().blue(), msg.bold());
 }
 
@@ -10,6 +35,9 @@ pub fn print_substep(msg: &str) {
 }
 
 pub fn print_success(msg: &str) {
+    if quiet() {
+        return;
+    }
     eprintln!("{} {}", "✓".bold().green(), msg.green());
 }
 
@@ -21,7 +49,111 @@ pub fn print_warning(msg: &str) {
     eprintln!("{} {}", "!".bold().yellow(), msg.yellow());
 }
 
+/// Warnings raised while pages are processed. They are printed together at
+/// the end of the run instead of interleaving with the progress bar.
+static DEFERRED_WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Hold a warning until the end of the run; safe to call from worker threads
+pub fn defer_warning(msg: String) {
+    DEFERRED_WARNINGS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(msg);
+}
+
+/// Take the warnings deferred so far, leaving none behind
+pub fn take_warnings() -> Vec<String> {
+    std::mem::take(&mut *DEFERRED_WARNINGS.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Print any deferred warnings as one section on stderr
+pub fn print_deferred_warnings() {
+    print_warning_section(&take_warnings());
+}
+
+fn print_warning_section(warnings: &[String]) {
+    if warnings.is_empty() {
+        return;
+    }
+    eprintln!(
+        "\n{}",
+        format!("Warnings ({}):", warnings.len()).bold().yellow()
+    );
+    for warning in warnings {
+        print_warning(warning);
+    }
+}
+
+/// One `key=value` line with the same fields for every command, for grepping logs
+pub fn format_final_status(
+    command: &str,
+    results: &ctag::models::ProcessResults,
+    duration: Duration,
+) -> String {
+    format!(
+        "ctag: command={} matched={} changed={} failed={} skipped={} duration={:.1}s",
+        command,
+        results.total,
+        results.success,
+        results.failed,
+        results.skipped,
+        duration.as_secs_f64()
+    )
+}
+
+/// Print the final status line on stderr; `--quiet` leaves it out
+pub fn print_final_status(
+    command: &str,
+    results: &ctag::models::ProcessResults,
+    duration: Duration,
+) {
+    if quiet() {
+        return;
+    }
+    eprintln!("{}", format_final_status(command, results, duration));
+}
+
+/// One `--live` line for a finished page, e.g. `✓ added [a, b] to Page (Space)`
+pub fn format_live_result(detail: &ctag::models::ActionDetail, success: bool) -> String {
+    let list = |tags: &[String]| format!("[{}]", tags.join(", "));
+    let page = format!("{} ({})", detail.url, detail.space);
+    let changes = match (detail.tags_added.is_empty(), detail.tags_removed.is_empty()) {
+        (false, true) => format!("added {} to", list(&detail.tags_added)),
+        (true, false) => format!("removed {} from", list(&detail.tags_removed)),
+        (false, false) => format!(
+            "added {}, removed {} on",
+            list(&detail.tags_added),
+            list(&detail.tags_removed)
+        ),
+        (true, true) => "updated".to_string(),
+    };
+    if success {
+        format!("{} {} {}", "✓".bold().green(), changes, page)
+    } else {
+        format!("{} {} {}", "✗".bold().red(), "failed on".red(), page)
+    }
+}
+
+/// Print a `--live` line above the progress bar, if there is one
+pub fn print_live_result(
+    progress: Option<&ProgressBar>,
+    detail: &ctag::models::ActionDetail,
+    success: bool,
+) {
+    if quiet() {
+        return;
+    }
+    let line = format_live_result(detail, success);
+    match progress {
+        Some(bar) => bar.suspend(|| eprintln!("{}", line)),
+        None => eprintln!("{}", line),
+    }
+}
+
 pub fn print_info(msg: &str) {
+    if quiet() {
+        return;
+    }
     eprintln!("{} {}", "i".bold().blue(), msg.blue());
 }
 
@@ -29,6 +161,24 @@ pub fn print_dry_run(msg: &str) {
     eprintln!("{} {}", "[DRY RUN]".bold().purple(), msg.dimmed());
 }
 
+/// Print the trailing line of a dry-run preview cut short by --preview-limit
+pub fn print_preview_remainder(remaining: usize) {
+    print_dry_run(&format!("...and {} more", format_count(remaining)));
+}
+
+/// Format a count with thousands separators (e.g. 4800 -> "4,800")
+pub fn format_count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
 pub fn create_progress_bar(len: u64) -> ProgressBar {
     let pb = ProgressBar::new(len);
     pb.set_style(
@@ -52,13 +202,71 @@ pub fn create_pagination_spinner(msg: &str) -> ProgressBar {
     pb
 }
 
+/// Turn a pagination spinner into a determinate bar once the server reports
+/// how many results to expect
+pub fn set_pagination_total(pb: &ProgressBar, total: u64) {
+    if pb.length() == Some(total) {
+        return;
+    }
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} {msg} [{bar:40.cyan/blue}] {pos}/{len} pages")
+            .unwrap()
+            .progress_chars("━╸ "),
+    );
+    pb.set_length(total);
+}
+
+/// CSV output settings from `--csv-bom` and `--csv-delimiter`
+#[derive(Clone, Copy)]
+pub struct CsvOptions {
+    /// Prefix output with a UTF-8 byte order mark so Excel detects the encoding
+    pub bom: bool,
+    pub delimiter: u8,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            bom: false,
+            delimiter: b',',
+        }
+    }
+}
+
+impl CsvOptions {
+    /// CSV writer using the configured delimiter, with the BOM already written
+    pub fn writer<W: std::io::Write>(&self, mut inner: W) -> std::io::Result<csv::Writer<W>> {
+        if self.bom {
+            inner.write_all("\u{feff}".as_bytes())?;
+        }
+        Ok(csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(inner))
+    }
+}
+
 // Formatters for results
-pub fn print_summary(results: &ctag::models::ProcessResults, format: ctag::models::OutputFormat) {
+pub fn print_summary(
+    results: &ctag::models::ProcessResults,
+    format: ctag::models::OutputFormat,
+    csv_options: &CsvOptions,
+) {
+    let warnings = take_warnings();
+    if NO_SUMMARY.load(Ordering::Relaxed) {
+        print_warning_section(&warnings);
+        return;
+    }
     match format {
         ctag::models::OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(results).unwrap());
+            let mut summary = serde_json::to_value(results).unwrap();
+            if let (Some(fields), false) = (summary.as_object_mut(), warnings.is_empty()) {
+                fields.insert("warnings".to_string(), warnings.into());
+            }
+            println!("{}", serde_json::to_string_pretty(&summary).unwrap());
         }
         ctag::models::OutputFormat::Csv => {
+            print_warning_section(&warnings);
             #[derive(serde::Serialize)]
             struct CsvSummary {
                 total: usize,
@@ -67,8 +275,11 @@ pub fn print_summary(results: &ctag::models::ProcessResults, format: ctag::model
                 success: usize,
                 failed: usize,
                 aborted: bool,
+                deadline_reached: bool,
                 tags_added: usize,
                 tags_removed: usize,
+                added_only: usize,
+                distinct_pages: usize,
             }
             let summary = CsvSummary {
                 total: results.total,
@@ -77,22 +288,53 @@ pub fn print_summary(results: &ctag::models::ProcessResults, format: ctag::model
                 success: results.success,
                 failed: results.failed,
                 aborted: results.aborted,
+                deadline_reached: results.deadline_reached,
                 tags_added: results.tags_added,
                 tags_removed: results.tags_removed,
+                added_only: results.added_only,
+                distinct_pages: results.distinct_pages,
             };
-            let mut wtr = csv::Writer::from_writer(std::io::stdout());
+            let mut wtr = csv_options.writer(std::io::stdout()).unwrap();
             wtr.serialize(summary).unwrap();
             wtr.flush().unwrap();
         }
         ctag::models::OutputFormat::Verbose => {
+            print_warning_section(&warnings);
+            print_subtotals(results);
             print_summary_table(results);
+            print_deadline_note(results);
         }
         ctag::models::OutputFormat::Simple => {
+            print_warning_section(&warnings);
+            print_subtotals(results);
             print_summary_minimal(results);
+            print_deadline_note(results);
         }
     }
 }
 
+fn print_subtotals(results: &ctag::models::ProcessResults) {
+    for sub in &results.subtotals {
+        println!(
+            "{} {}/{} commands, {} failed, {} pages",
+            format!("{}:", sub.source).bold(),
+            sub.success.to_string().green(),
+            sub.total,
+            sub.failed.to_string().red(),
+            sub.distinct_pages.to_string().cyan()
+        );
+    }
+}
+
+fn print_deadline_note(results: &ctag::models::ProcessResults) {
+    if results.deadline_reached {
+        print_warning(&format!(
+            "Deadline reached: stopped after {} of {} pages.",
+            results.processed, results.total
+        ));
+    }
+}
+
 fn print_summary_table(results: &ctag::models::ProcessResults) {
     use comfy_table::modifiers::UTF8_ROUND_CORNERS;
     use comfy_table::presets::UTF8_FULL;
@@ -115,6 +357,12 @@ fn print_summary_table(results: &ctag::models::ProcessResults) {
         Cell::new("Total Pages Found").add_attribute(Attribute::Bold),
         Cell::new(results.total.to_string()).fg(Color::White),
     ]);
+    if results.distinct_pages > 0 {
+        table.add_row(vec![
+            Cell::new("Distinct Pages"),
+            Cell::new(results.distinct_pages.to_string()).fg(Color::White),
+        ]);
+    }
     table.add_row(vec![
         Cell::new("Processed").fg(Color::Blue),
         Cell::new(results.processed.to_string()).fg(Color::Blue),
@@ -127,6 +375,12 @@ fn print_summary_table(results: &ctag::models::ProcessResults) {
         Cell::new("Successful").fg(Color::Green),
         Cell::new(results.success.to_string()).fg(Color::Green),
     ]);
+    if results.added_only > 0 {
+        table.add_row(vec![
+            Cell::new("  of which Added Only").fg(Color::Green),
+            Cell::new(results.added_only.to_string()).fg(Color::Green),
+        ]);
+    }
     table.add_row(vec![
         Cell::new("Failed").fg(Color::Red),
         Cell::new(results.failed.to_string()).fg(Color::Red),
@@ -162,6 +416,14 @@ pub fn print_summary_minimal(results: &ctag::models::ProcessResults) {
         ));
     }
 
+    if results.added_only > 0 {
+        parts.push(format!(
+            "{} {}",
+            "Added Only:".bold(),
+            results.added_only.to_string().green()
+        ));
+    }
+
     if results.failed > 0 {
         parts.push(format!(
             "{} {}",
@@ -230,16 +492,6 @@ pub fn make_clickable(text: &str, url: &str) -> String {
     format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
 }
 
-/// Create a clickable page link using page ID
-pub fn make_page_clickable(text: &str, page_id: &str, base_url: &str) -> String {
-    let url = format!(
-        "{}/wiki/pages/viewpage.action?pageId={}",
-        base_url.trim_end_matches('/'),
-        page_id
-    );
-    make_clickable(text, &url)
-}
-
 /// Build a page path like /Space/Parent/Child/PageTitle
 pub fn build_page_path(space: &str, ancestors: &[String], title: &str) -> String {
     let mut parts = vec![space.to_string()];
@@ -248,6 +500,17 @@ pub fn build_page_path(space: &str, ancestors: &[String], title: &str) -> String
     format!("/{}", parts.join("/"))
 }
 
+/// Print a page's tag changes as `+tag` / `-tag` lines under its path
+pub fn print_tag_diff(path: &str, added: &[String], removed: &[String]) {
+    println!("{}", path.bright_white());
+    for tag in added {
+        println!("  {}", format!("+{}", tag).green());
+    }
+    for tag in removed {
+        println!("  {}", format!("-{}", tag).red());
+    }
+}
+
 /// Format a space name with color (bold cyan)
 pub fn format_space(space: &str) -> String {
     format!("\x1b[1;36m{}\x1b[0m", space)
@@ -257,3 +520,73 @@ pub fn format_space(space: &str) -> String {
 pub fn format_directory(name: &str) -> String {
     format!("\x1b[1;34m{}\x1b[0m", name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        defer_warning, format_count, format_final_status, format_live_result, take_warnings,
+        CsvOptions,
+    };
+
+    #[test]
+    fn deferred_warnings_are_taken_once() {
+        defer_warning("first".to_string());
+        defer_warning("second".to_string());
+        assert_eq!(take_warnings(), vec!["first", "second"]);
+        assert!(take_warnings().is_empty());
+    }
+
+    #[test]
+    fn final_status_line_has_fixed_keys() {
+        let mut results = ctag::models::ProcessResults::new(10);
+        results.success = 7;
+        results.failed = 1;
+        results.skipped = 2;
+        assert_eq!(
+            format_final_status("add", &results, std::time::Duration::from_millis(12_340)),
+            "ctag: command=add matched=10 changed=7 failed=1 skipped=2 duration=12.3s"
+        );
+    }
+
+    #[test]
+    fn live_result_names_the_changes_and_page() {
+        let detail = ctag::models::ActionDetail {
+            page_id: "1".to_string(),
+            title: "Page".to_string(),
+            space: "Docs".to_string(),
+            url: "Page".to_string(),
+            tags_added: vec!["a".to_string(), "b".to_string()],
+            tags_removed: Vec::new(),
+            tag_results: Vec::new(),
+        };
+        assert!(format_live_result(&detail, true).ends_with("added [a, b] to Page (Docs)"));
+        let swapped = ctag::models::ActionDetail {
+            tags_removed: vec!["c".to_string()],
+            ..detail.clone()
+        };
+        assert!(format_live_result(&swapped, true)
+            .ends_with("added [a, b], removed [c] on Page (Docs)"));
+        let failed = format_live_result(&detail, false);
+        assert!(failed.contains("failed on") && failed.ends_with("Page (Docs)"));
+    }
+
+    #[test]
+    fn format_count_inserts_thousands_separators() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(4800), "4,800");
+        assert_eq!(format_count(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn csv_writer_applies_bom_and_delimiter() {
+        let options = CsvOptions {
+            bom: true,
+            delimiter: b';',
+        };
+        let mut wtr = options.writer(vec![]).unwrap();
+        wtr.write_record(["tag", "café"]).unwrap();
+        let out = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(out, "\u{feff}tag;café\n");
+    }
+}
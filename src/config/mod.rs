@@ -0,0 +1,164 @@
+//! Optional TOML configuration file.
+//!
+//! Looked up at `--config`, then `$CTAG_CONFIG`, then
+//! `$XDG_CONFIG_HOME/ctag/config.toml` (or `~/.config/ctag/config.toml`).
+//! A missing file is the same as an empty one.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_TOKEN_URL: &str = "https://auth.atlassian.com/oauth/token";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// OAuth 2.0 (3LO) credentials; when present they replace Basic auth
+    #[serde(default)]
+    pub oauth: Option<OAuthConfig>,
+    /// Named CQL queries, referenced with `--cql-name`
+    #[serde(default)]
+    pub queries: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthConfig {
+    pub access_token: String,
+    pub refresh_token: String,
+    #[serde(default = "default_token_url")]
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+fn default_token_url() -> String {
+    DEFAULT_TOKEN_URL.to_string()
+}
+
+impl Config {
+    /// Resolve the config file location from an explicit path or the environment
+    pub fn path(explicit: Option<&Path>) -> Option<PathBuf> {
+        if let Some(path) = explicit {
+            return Some(path.to_path_buf());
+        }
+        if let Ok(path) = std::env::var("CTAG_CONFIG") {
+            if !path.is_empty() {
+                return Some(PathBuf::from(path));
+            }
+        }
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+        Some(config_dir.join("ctag").join("config.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read config file: {}", path.display()))?;
+        Self::parse(&content).context(format!("Failed to parse config file: {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Look up a named query from the `[queries]` table
+    pub fn query(&self, name: &str) -> Result<&str> {
+        match self.queries.get(name) {
+            Some(cql) => Ok(cql),
+            None if self.queries.is_empty() => {
+                anyhow::bail!("Unknown query '{}': no [queries] in the config file", name)
+            }
+            None => anyhow::bail!(
+                "Unknown query '{}' (available: {})",
+                name,
+                self.queries.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+/// Write the `[oauth]` table to the config file, keeping every other setting.
+pub fn save_oauth(path: &Path, oauth: &OAuthConfig) -> Result<()> {
+    let mut table: toml::Table = if path.exists() {
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read config file: {}", path.display()))?;
+        content
+            .parse()
+            .context(format!("Failed to parse config file: {}", path.display()))?
+    } else {
+        toml::Table::new()
+    };
+    table.insert("oauth".to_string(), toml::Value::try_from(oauth)?);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string(&table)?)
+        .context(format!("Failed to write config file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_oauth_defaults_token_url() {
+        let config = Config::parse(
+            r#"
+            [oauth]
+            access_token = "a"
+            refresh_token = "r"
+            client_id = "id"
+            client_secret = "secret"
+            "#,
+        )
+        .unwrap();
+        let oauth = config.oauth.unwrap();
+        assert_eq!(oauth.token_url, DEFAULT_TOKEN_URL);
+        assert!(Config::parse("").unwrap().oauth.is_none());
+    }
+
+    #[test]
+    fn query_looks_up_named_cql() {
+        let config = Config::parse(
+            r#"
+            [queries]
+            stale = "space = DOCS AND lastmodified < -365d"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.query("stale").unwrap(),
+            "space = DOCS AND lastmodified < -365d"
+        );
+        let err = config.query("missing").unwrap_err().to_string();
+        assert!(err.contains("available: stale"));
+    }
+
+    #[test]
+    fn save_oauth_keeps_other_sections() {
+        let path = std::env::temp_dir().join(format!("ctag-config-{}.toml", std::process::id()));
+        fs::write(&path, "[other]\nkey = \"kept\"\n").unwrap();
+
+        let oauth = OAuthConfig {
+            access_token: "new-access".to_string(),
+            refresh_token: "new-refresh".to_string(),
+            token_url: DEFAULT_TOKEN_URL.to_string(),
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+        };
+        save_oauth(&path, &oauth).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert!(content.contains("kept"));
+        let config = Config::parse(&content).unwrap();
+        assert_eq!(config.oauth.unwrap().access_token, "new-access");
+    }
+}
@@ -0,0 +1,126 @@
+//! Helpers for assembling CQL expressions from structured input.
+
+/// Quote a value as a CQL string literal, escaping embedded quotes and backslashes.
+pub fn quote(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+/// Combine two CQL expressions so pages must match both. An empty side is
+/// left out rather than producing `() AND (...)`.
+pub fn and(left: &str, right: &str) -> String {
+    match (left.trim(), right.trim()) {
+        ("", other) | (other, "") => other.to_string(),
+        (left, right) => format!("({}) AND ({})", left, right),
+    }
+}
+
+/// Match pages carrying any of `labels`, e.g. `label in ("a", "b")`
+pub fn label_in(labels: &[String]) -> String {
+    let quoted: Vec<String> = labels.iter().map(|l| quote(l)).collect();
+    format!("label in ({})", quoted.join(", "))
+}
+
+/// Match pages in any of the spaces `keys`, e.g. `space in ("A", "B")`
+pub fn space_in(keys: &[String]) -> String {
+    let quoted: Vec<String> = keys.iter().map(|k| quote(k)).collect();
+    format!("space in ({})", quoted.join(", "))
+}
+
+/// Structured description of a page query. Empty fields are left out of the
+/// generated CQL.
+#[derive(Debug, Default, Clone)]
+pub struct CqlBuilder {
+    pub space: Option<String>,
+    pub content_type: Option<String>,
+    pub label: Option<String>,
+    pub text: Option<String>,
+    /// Relative (`-7d`) or absolute (`2024-01-31`) date for `lastmodified >=`
+    pub modified_since: Option<String>,
+}
+
+impl CqlBuilder {
+    /// Join all non-empty conditions with `AND`. Returns `None` when no
+    /// condition was given, since an empty CQL expression is rejected by the API.
+    pub fn build(&self) -> Option<String> {
+        let mut clauses = Vec::new();
+        if let Some(space) = non_empty(&self.space) {
+            clauses.push(format!("space = {}", quote(space)));
+        }
+        if let Some(content_type) = non_empty(&self.content_type) {
+            clauses.push(format!("type = {}", quote(content_type)));
+        }
+        if let Some(label) = non_empty(&self.label) {
+            clauses.push(format!("label = {}", quote(label)));
+        }
+        if let Some(text) = non_empty(&self.text) {
+            clauses.push(format!("text ~ {}", quote(text)));
+        }
+        if let Some(since) = non_empty(&self.modified_since) {
+            // Relative dates like -7d are CQL functions, not string literals
+            if since.starts_with('-') || since.starts_with('+') {
+                clauses.push(format!("lastmodified >= {}", since));
+            } else {
+                clauses.push(format!("lastmodified >= {}", quote(since)));
+            }
+        }
+        if clauses.is_empty() {
+            None
+        } else {
+            Some(clauses.join(" AND "))
+        }
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_joins_non_empty_clauses() {
+        let builder = CqlBuilder {
+            space: Some("DOCS".to_string()),
+            content_type: Some("page".to_string()),
+            label: Some(" ".to_string()),
+            text: Some("release \"notes\"".to_string()),
+            modified_since: Some("-7d".to_string()),
+        };
+        assert_eq!(
+            builder.build().unwrap(),
+            r#"space = "DOCS" AND type = "page" AND text ~ "release \"notes\"" AND lastmodified >= -7d"#
+        );
+    }
+
+    #[test]
+    fn and_leaves_out_empty_sides() {
+        assert_eq!(
+            and("space = A", " type = page "),
+            "(space = A) AND (type = page)"
+        );
+        assert_eq!(and("", "type = page"), "type = page");
+        assert_eq!(and("space = A", " "), "space = A");
+    }
+
+    #[test]
+    fn label_in_quotes_each_label() {
+        let labels = vec!["status:done".to_string(), "say \"hi\"".to_string()];
+        assert_eq!(
+            label_in(&labels),
+            r#"label in ("status:done", "say \"hi\"")"#
+        );
+    }
+
+    #[test]
+    fn build_quotes_absolute_dates_and_rejects_empty() {
+        let builder = CqlBuilder {
+            modified_since: Some("2024-01-31".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(builder.build().unwrap(), r#"lastmodified >= "2024-01-31""#);
+        assert_eq!(CqlBuilder::default().build(), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! Append-only journal of applied tag changes, one JSON object per line.
+//!
+//! Each record is serialized in full and handed to the OS with a single
+//! append before the call returns, so a killed run leaves every completed
+//! record intact and at most loses the page that was in flight. With `sync`
+//! the record is also fsynced, which survives power loss at the cost of a
+//! disk flush per page.
+//!
+//! The same format serves as a checkpoint: `completed_page_ids` reads back
+//! the pages a killed run finished so a restart can skip them.
+
+use crate::models::ActionDetail;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// RFC 3339 time the record was written
+    pub timestamp: String,
+    pub page_id: String,
+    pub title: String,
+    pub tags_added: Vec<String>,
+    pub tags_removed: Vec<String>,
+    pub success: bool,
+}
+
+impl JournalEntry {
+    pub fn from_detail(detail: &ActionDetail, success: bool) -> Self {
+        Self {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            page_id: detail.page_id.clone(),
+            title: detail.title.clone(),
+            tags_added: detail.tags_added.clone(),
+            tags_removed: detail.tags_removed.clone(),
+            success,
+        }
+    }
+
+    /// A record for a page the run finished without changing any tags
+    pub fn unchanged(page_id: &str, title: &str, success: bool) -> Self {
+        Self {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            page_id: page_id.to_string(),
+            title: title.to_string(),
+            tags_added: Vec::new(),
+            tags_removed: Vec::new(),
+            success,
+        }
+    }
+}
+
+/// Ids of the pages recorded as successful in a journal or checkpoint file.
+/// A missing file means nothing is done yet; a torn last line from a killed
+/// run is ignored.
+pub fn completed_page_ids(path: &Path) -> Result<HashSet<String>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e).context(format!("Failed to read checkpoint: {}", path.display())),
+    };
+    let mut done = HashSet::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.context(format!("Failed to read checkpoint: {}", path.display()))?;
+        if let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) {
+            if entry.success {
+                done.insert(entry.page_id);
+            }
+        }
+    }
+    Ok(done)
+}
+
+pub struct Journal {
+    file: Mutex<File>,
+    sync: bool,
+}
+
+impl Journal {
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: &Path, sync: bool) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format!("Failed to open journal: {}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(file),
+            sync,
+        })
+    }
+
+    /// Write one record as a single line and flush it before returning
+    pub fn record(&self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        // One write per record keeps lines whole even when pages finish concurrently
+        file.write_all(&line)?;
+        file.flush()?;
+        if self.sync {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_appends_one_line_per_entry() {
+        let path = std::env::temp_dir().join(format!("ctag-journal-{}.jsonl", std::process::id()));
+        std::fs::remove_file(&path).ok();
+
+        let detail = ActionDetail {
+            page_id: "42".to_string(),
+            title: "Page".to_string(),
+            space: "Docs".to_string(),
+            url: String::new(),
+            tags_added: vec!["new".to_string()],
+            tags_removed: vec!["old".to_string()],
+            tag_results: Vec::new(),
+        };
+        let journal = Journal::open(&path, true).unwrap();
+        journal
+            .record(&JournalEntry::from_detail(&detail, true))
+            .unwrap();
+        drop(journal);
+        // Reopening appends rather than truncating
+        let journal = Journal::open(&path, false).unwrap();
+        journal
+            .record(&JournalEntry::from_detail(&detail, false))
+            .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let entries: Vec<JournalEntry> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].tags_removed, vec!["old"]);
+        assert!(entries[0].success);
+        assert!(!entries[1].success);
+    }
+
+    #[test]
+    fn completed_page_ids_keeps_successes_and_skips_torn_lines() {
+        let path =
+            std::env::temp_dir().join(format!("ctag-checkpoint-{}.jsonl", std::process::id()));
+        assert!(completed_page_ids(&path).unwrap().is_empty());
+
+        let journal = Journal::open(&path, false).unwrap();
+        journal
+            .record(&JournalEntry::unchanged("1", "One", true))
+            .unwrap();
+        journal
+            .record(&JournalEntry::unchanged("2", "Two", false))
+            .unwrap();
+        drop(journal);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"timestamp\":\"2024").unwrap();
+
+        let done = completed_page_ids(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(done, HashSet::from(["1".to_string()]));
+    }
+}
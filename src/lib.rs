@@ -1,6 +1,11 @@
+pub mod aliases;
 pub mod api;
+pub mod config;
+pub mod cql;
+pub mod journal;
 pub mod models;
 pub mod ops;
+pub mod rules;
 
 // Re-export common types
 pub use api::ConfluenceClient;
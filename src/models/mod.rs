@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 #[derive(clap::ValueEnum, Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
 pub enum OutputFormat {
@@ -27,9 +28,67 @@ pub struct SearchResultItem {
     pub space: Option<Space>,
     #[serde(rename = "resultGlobalContainer")]
     pub result_global_container: Option<GlobalContainer>,
+    /// Web UI path relative to the context path, as returned by search
+    #[serde(default)]
+    pub url: Option<String>,
 }
 
 impl SearchResultItem {
+    /// Minimal result for a page known only by id, for id-driven operations
+    /// that skip the CQL search.
+    pub fn from_page_id(id: &str, title: Option<String>) -> Self {
+        Self {
+            content: Some(Content {
+                id: Some(id.to_string()),
+                title: title.clone(),
+                content_type: Some("page".to_string()),
+                status: None,
+                space: None,
+                ancestors: vec![],
+                version: None,
+                history: None,
+                metadata: None,
+            }),
+            title,
+            space: None,
+            result_global_container: None,
+            url: None,
+        }
+    }
+
+    /// Result for content fetched directly by id rather than through search
+    pub fn from_content(content: Content, url: Option<String>) -> Self {
+        Self {
+            title: content.title.clone(),
+            space: content.space.clone(),
+            content: Some(content),
+            result_global_container: None,
+            url,
+        }
+    }
+
+    /// Content type from the search result (`page`, `blogpost`, `attachment`...),
+    /// `page` when unknown
+    pub fn content_type(&self) -> &str {
+        self.content
+            .as_ref()
+            .and_then(|c| c.content_type.as_deref())
+            .unwrap_or("page")
+    }
+
+    /// Browser URL of the content. Attachments link to the file via the
+    /// search result's path; everything else to the page view by id.
+    /// `web_base` includes the context path, as `ConfluenceClient::web_base_url`.
+    pub fn web_url(&self, web_base: &str) -> Option<String> {
+        let web_base = web_base.trim_end_matches('/');
+        match (self.content_type(), &self.url) {
+            ("attachment", Some(path)) => Some(format!("{}{}", web_base, path)),
+            _ => self
+                .page_id()
+                .map(|id| format!("{}/pages/viewpage.action?pageId={}", web_base, id)),
+        }
+    }
+
     pub fn space_name(&self) -> &str {
         self.content
             .as_ref()
@@ -44,26 +103,59 @@ impl SearchResultItem {
             .unwrap_or("Unknown")
     }
 
+    /// Stable space key as used in CQL, when the result carries it
+    pub fn space_key(&self) -> Option<&str> {
+        self.content
+            .as_ref()
+            .and_then(|c| c.space.as_ref())
+            .and_then(|s| s.key.as_deref())
+            .or_else(|| self.space.as_ref().and_then(|s| s.key.as_deref()))
+    }
+
     pub fn page_id(&self) -> Option<&str> {
         self.content.as_ref().and_then(|c| c.id.as_deref())
     }
 
-    pub fn printable_clickable_title(&self, base_url: &str) -> String {
+    pub fn printable_clickable_title(&self, web_base: &str) -> String {
         let title = self.title.as_deref().unwrap_or("Unknown");
         let sanitized = sanitize_text(title);
-        if let Some(id) = self.page_id() {
-            format!(
-                "\x1b]8;;{}/wiki/pages/viewpage.action?pageId={}\x1b\\{}\x1b]8;;\x1b\\",
-                base_url.trim_end_matches('/'),
-                id,
-                sanitized
-            )
+        if let Some(url) = self.web_url(web_base) {
+            format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, sanitized)
         } else {
             sanitized
         }
     }
 }
 
+/// Page id from a bare id or a page URL copied from the browser, e.g.
+/// `.../pages/viewpage.action?pageId=12345` or
+/// `.../wiki/spaces/DOCS/pages/12345/Title`
+pub fn page_id_from_ref(input: &str) -> anyhow::Result<String> {
+    let input = input.trim();
+    if !input.is_empty() && input.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(input.to_string());
+    }
+    let url = url::Url::parse(input)
+        .map_err(|_| anyhow::anyhow!("'{}' is neither a page id nor a URL", input))?;
+    if let Some((_, id)) = url.query_pairs().find(|(key, _)| key == "pageId") {
+        if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(id.into_owned());
+        }
+    }
+    // Cloud paths: /spaces/KEY/pages/ID/Title and /pages/edit-v2/ID
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    segments
+        .iter()
+        .skip_while(|s| **s != "pages")
+        .skip(1)
+        .find(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+        .map(|id| id.to_string())
+        .ok_or_else(|| anyhow::anyhow!("Could not find a page id in URL '{}'", input))
+}
+
 /// Sanitize text by decoding HTML entities and removing control characters (except whitespace)
 pub fn sanitize_text(text: &str) -> String {
     // First decode HTML entities (e.g., &#128274; -> 🔒)
@@ -91,6 +183,65 @@ pub struct Content {
     pub space: Option<Space>,
     #[serde(default)]
     pub ancestors: Vec<Ancestor>,
+    /// Present when the search or lookup expanded `version`
+    #[serde(default)]
+    pub version: Option<Version>,
+    /// Present when the search or lookup expanded `history`
+    #[serde(default)]
+    pub history: Option<History>,
+    /// Present when the search or lookup expanded `metadata.labels`
+    #[serde(default)]
+    pub metadata: Option<Metadata>,
+}
+
+impl Content {
+    /// The labels from an expanded `metadata.labels`, or `None` when they
+    /// weren't expanded or the expansion holds only the first batch
+    pub fn expanded_labels(&self) -> Option<LabelsResponse> {
+        let labels = self.metadata.as_ref()?.labels.as_ref()?;
+        let has_next = labels
+            .links
+            .as_ref()
+            .is_some_and(|links| links.get("next").is_some());
+        let filled =
+            matches!((labels.size, labels.limit), (Some(size), Some(limit)) if size >= limit);
+        if has_next || filled {
+            return None;
+        }
+        Some(LabelsResponse {
+            results: labels.results.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metadata {
+    #[serde(default)]
+    pub labels: Option<ExpandedLabels>,
+}
+
+/// One batch of labels embedded in a content response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpandedLabels {
+    #[serde(default)]
+    pub results: Vec<Label>,
+    pub size: Option<usize>,
+    pub limit: Option<usize>,
+    #[serde(rename = "_links")]
+    pub links: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Version {
+    pub number: Option<u64>,
+    /// When this version was saved, i.e. the last modification
+    pub when: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct History {
+    pub created_date: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -121,6 +272,21 @@ pub struct CqlResponse {
 pub struct Label {
     pub name: String,
     pub id: Option<String>,
+    /// Label namespace: `global`, `team`, `my` or `system`
+    #[serde(default)]
+    pub prefix: Option<String>,
+}
+
+impl Label {
+    /// The label as written in CQL: `team:onboarding`, or just the name for
+    /// global labels
+    pub fn prefixed_name(&self) -> String {
+        let name = sanitize_text(&self.name);
+        match self.prefix.as_deref() {
+            None | Some("") | Some("global") => name,
+            Some(prefix) => format!("{}:{}", prefix, name),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -128,6 +294,21 @@ pub struct LabelsResponse {
     pub results: Vec<Label>,
 }
 
+impl LabelsResponse {
+    /// Label names as shown in the Confluence UI, with HTML entities decoded
+    pub fn names(self) -> Vec<String> {
+        self.results
+            .into_iter()
+            .map(|l| sanitize_text(&l.name))
+            .collect()
+    }
+
+    /// Label names with their non-global prefix, e.g. `team:onboarding`
+    pub fn prefixed_names(self) -> Vec<String> {
+        self.results.iter().map(Label::prefixed_name).collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActionDetail {
     pub page_id: String,
@@ -136,6 +317,31 @@ pub struct ActionDetail {
     pub url: String,
     pub tags_added: Vec<String>,
     pub tags_removed: Vec<String>,
+    /// Per-tag outcome of the command's add or remove
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_results: Vec<TagResult>,
+}
+
+/// Outcome of applying a single tag to a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagResult {
+    pub tag: String,
+    pub success: bool,
+}
+
+impl TagResult {
+    pub fn all_succeeded(results: &[TagResult]) -> bool {
+        results.iter().all(|r| r.success)
+    }
+
+    /// Tags that were applied successfully
+    pub fn succeeded(results: &[TagResult]) -> Vec<String> {
+        results
+            .iter()
+            .filter(|r| r.success)
+            .map(|r| r.tag.clone())
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -146,15 +352,80 @@ pub struct ProcessResults {
     pub success: usize,
     pub failed: usize,
     pub aborted: bool,
+    /// The run stopped starting new pages once `--deadline` passed
+    #[serde(default)]
+    pub deadline_reached: bool,
     #[serde(default)]
     pub tags_added: usize,
     #[serde(default)]
     pub tags_removed: usize,
+    /// Pages `replace --add-if-missing` gave a new tag without renaming one
+    #[serde(skip_serializing_if = "is_zero", default)]
+    pub added_only: usize,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub details: Vec<ActionDetail>,
+    /// Distinct pages acted on; `total` counts a page once per match
+    #[serde(default)]
+    pub distinct_pages: usize,
+    /// Ids behind `distinct_pages`, kept so results can be merged
+    #[serde(skip)]
+    pub page_ids: HashSet<String>,
+    /// Per-input counts when one run combines several inputs
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub subtotals: Vec<Subtotal>,
+    /// Results of a `--dry-run` preview rather than of writes
+    #[serde(skip)]
+    pub dry_run: bool,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Counts for one input of a combined run, such as one `from-json` file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subtotal {
+    pub source: String,
+    pub total: usize,
+    pub processed: usize,
+    pub success: usize,
+    pub failed: usize,
+    pub distinct_pages: usize,
 }
 
 impl ProcessResults {
+    /// Exit code describing the outcome, used with `--rich-exit-codes`:
+    /// 0 = all succeeded, 1 = partial failures, 2 = all failed,
+    /// 3 = aborted, 4 = no pages matched, 5 = deadline reached,
+    /// 6 = every matched page already needed no change.
+    pub fn exit_code(&self) -> i32 {
+        if self.deadline_reached {
+            5
+        } else if self.aborted {
+            3
+        } else if self.total == 0 {
+            4
+        } else if self.dry_run && self.skipped == self.total {
+            6
+        } else if self.failed == 0 {
+            0
+        } else if self.success == 0 {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Results of a dry run over `total` pages, `unchanged` of which were
+    /// checked and need no change. Pages past the preview limit are not
+    /// checked, so only a full preview can report that nothing would change.
+    pub fn preview(total: usize, unchanged: usize) -> Self {
+        let mut results = Self::new(total);
+        results.skipped = unchanged;
+        results.dry_run = true;
+        results
+    }
+
     pub fn new(total: usize) -> Self {
         Self {
             total,
@@ -163,16 +434,138 @@ impl ProcessResults {
             success: 0,
             failed: 0,
             aborted: false,
+            deadline_reached: false,
             tags_added: 0,
             tags_removed: 0,
+            added_only: 0,
             details: Vec::new(),
+            distinct_pages: 0,
+            page_ids: HashSet::new(),
+            subtotals: Vec::new(),
+            dry_run: false,
         }
     }
+
+    /// Record matched page ids, updating `distinct_pages`
+    pub fn record_pages<'a>(&mut self, ids: impl IntoIterator<Item = &'a str>) {
+        for id in ids {
+            if !self.page_ids.contains(id) {
+                self.page_ids.insert(id.to_string());
+            }
+        }
+        self.distinct_pages = self.page_ids.len();
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ProcessResults;
+    use super::{
+        page_id_from_ref, ActionDetail, Content, ProcessResults, SearchResultItem, TagResult,
+    };
+
+    #[test]
+    fn page_id_from_ref_accepts_ids_and_page_urls() {
+        let id = |input: &str| page_id_from_ref(input).unwrap();
+        assert_eq!(id(" 12345 "), "12345");
+        assert_eq!(
+            id("https://x.atlassian.net/wiki/pages/viewpage.action?pageId=12345"),
+            "12345"
+        );
+        assert_eq!(
+            id("https://x.atlassian.net/wiki/spaces/DOCS/pages/12345/Release+notes"),
+            "12345"
+        );
+        assert_eq!(
+            id("https://x.atlassian.net/wiki/spaces/DOCS/pages/edit-v2/12345"),
+            "12345"
+        );
+        assert!(page_id_from_ref("https://x.atlassian.net/wiki/x/AbCd").is_err());
+        assert!(page_id_from_ref("release-notes").is_err());
+    }
+
+    #[test]
+    fn web_url_links_attachments_to_their_file() {
+        let mut item: SearchResultItem = serde_json::from_value(serde_json::json!({
+            "content": {"id": "att9", "type": "attachment"},
+            "title": "spec.pdf",
+            "url": "/download/attachments/42/spec.pdf?version=1"
+        }))
+        .unwrap();
+        assert_eq!(item.content_type(), "attachment");
+        assert_eq!(
+            item.web_url("https://example.atlassian.net/wiki/").unwrap(),
+            "https://example.atlassian.net/wiki/download/attachments/42/spec.pdf?version=1"
+        );
+        item.content.as_mut().unwrap().content_type = Some("page".to_string());
+        assert_eq!(
+            item.web_url("https://example.atlassian.net/wiki").unwrap(),
+            "https://example.atlassian.net/wiki/pages/viewpage.action?pageId=att9"
+        );
+    }
+
+    #[test]
+    fn web_url_follows_the_context_path() {
+        let item: SearchResultItem = serde_json::from_value(serde_json::json!({
+            "content": {"id": "42", "type": "page"},
+            "title": "Home"
+        }))
+        .unwrap();
+        assert_eq!(
+            item.web_url("https://confluence.example.com/confluence")
+                .unwrap(),
+            "https://confluence.example.com/confluence/pages/viewpage.action?pageId=42"
+        );
+        assert_eq!(
+            item.web_url("https://confluence.example.com").unwrap(),
+            "https://confluence.example.com/pages/viewpage.action?pageId=42"
+        );
+    }
+
+    #[test]
+    fn expanded_labels_are_used_only_when_complete() {
+        let content = |labels: serde_json::Value| -> Content {
+            serde_json::from_value(serde_json::json!({
+                "id": "1",
+                "title": "Page",
+                "metadata": {"labels": labels}
+            }))
+            .unwrap()
+        };
+        let complete = content(serde_json::json!({
+            "results": [{"name": "a", "prefix": "global"}, {"name": "b", "prefix": "team"}],
+            "size": 2,
+            "limit": 200
+        }));
+        let labels = complete.expanded_labels().unwrap();
+        assert_eq!(labels.prefixed_names(), vec!["a", "team:b"]);
+
+        let truncated = content(serde_json::json!({
+            "results": [{"name": "a"}],
+            "size": 1,
+            "limit": 1,
+            "_links": {"next": "/rest/api/content/1/label?start=1"}
+        }));
+        assert!(truncated.expanded_labels().is_none());
+
+        let plain: Content = serde_json::from_value(serde_json::json!({"id": "1"})).unwrap();
+        assert!(plain.expanded_labels().is_none());
+    }
+
+    #[test]
+    fn record_pages_counts_distinct_ids_across_merges() {
+        let mut first = ProcessResults::new(3);
+        first.record_pages(["1", "2", "1"]);
+        assert_eq!(first.distinct_pages, 2);
+
+        let mut second = ProcessResults::new(2);
+        second.record_pages(["2", "3"]);
+        first.record_pages(second.page_ids.iter().map(String::as_str));
+        assert_eq!(first.distinct_pages, 3);
+
+        let json = serde_json::to_value(&first).unwrap();
+        assert_eq!(json["distinct_pages"], 3);
+        assert!(json.get("page_ids").is_none());
+    }
 
     #[test]
     fn process_results_new_initializes_counts_correctly() {
@@ -186,4 +579,66 @@ mod tests {
         assert_eq!(pr.tags_added, 0);
         assert_eq!(pr.tags_removed, 0);
     }
+
+    #[test]
+    fn tag_results_serialize_in_action_detail() {
+        let tag_results = vec![
+            TagResult {
+                tag: "a".to_string(),
+                success: true,
+            },
+            TagResult {
+                tag: "b".to_string(),
+                success: false,
+            },
+        ];
+        assert!(!TagResult::all_succeeded(&tag_results));
+        let detail = ActionDetail {
+            page_id: "1".to_string(),
+            title: "Page".to_string(),
+            space: "Docs".to_string(),
+            url: String::new(),
+            tags_added: TagResult::succeeded(&tag_results),
+            tags_removed: vec![],
+            tag_results,
+        };
+        let json = serde_json::to_value(&detail).unwrap();
+        assert_eq!(json["tags_added"], serde_json::json!(["a"]));
+        assert_eq!(json["tag_results"][1]["tag"], "b");
+        assert_eq!(json["tag_results"][1]["success"], false);
+    }
+
+    #[test]
+    fn exit_code_maps_outcomes() {
+        let mut pr = ProcessResults::new(3);
+        pr.success = 3;
+        assert_eq!(pr.exit_code(), 0);
+
+        pr.success = 2;
+        pr.failed = 1;
+        assert_eq!(pr.exit_code(), 1);
+
+        pr.success = 0;
+        pr.failed = 3;
+        assert_eq!(pr.exit_code(), 2);
+
+        pr.aborted = true;
+        assert_eq!(pr.exit_code(), 3);
+
+        pr.deadline_reached = true;
+        assert_eq!(pr.exit_code(), 5);
+
+        assert_eq!(ProcessResults::new(0).exit_code(), 4);
+
+        // Dry runs: 0 when something would change, 6 when nothing would
+        assert_eq!(ProcessResults::preview(3, 1).exit_code(), 0);
+        assert_eq!(ProcessResults::preview(3, 3).exit_code(), 6);
+        assert_eq!(ProcessResults::new(3).exit_code(), 0);
+
+        // A real run whose pages were all skipped (filtered, not editable,
+        // declined) keeps the match-based code
+        let mut real = ProcessResults::new(3);
+        real.skipped = 3;
+        assert_eq!(real.exit_code(), 0);
+    }
 }
@@ -92,9 +92,15 @@ where
         success: success_count.load(Ordering::Relaxed),
         failed: failed_count.load(Ordering::Relaxed),
         aborted: false,
+        deadline_reached: false,
         tags_added: added_count.load(Ordering::Relaxed),
         tags_removed: removed_count.load(Ordering::Relaxed),
+        added_only: 0,
         details: details.into_inner().unwrap_or_default(),
+        distinct_pages: 0,
+        page_ids: Default::default(),
+        subtotals: Vec::new(),
+        dry_run: false,
     }
 }
 
@@ -106,12 +112,19 @@ pub fn get_matching_pages(
 ) -> Result<Vec<SearchResultItem>> {
     if let Some(p) = progress_reporter {
         p.message(&format!("Finding pages matching: {}", cql));
+        let mut reported = 0;
         client.get_all_cql_results_with_progress(
             cql,
             limit,
-            Some(|count, _| {
-                p.set_total(count as u64 + 100); // Rough estimate fix or just set position
-                                                 // Note: the original progress bar logic was "spinner", so "set_position" just spins
+            crate::api::SEARCH_EXPAND,
+            Some(|count: usize, total: Option<usize>| {
+                // Without totalSize there is nothing to size the bar by; only
+                // the count advances
+                if let Some(total) = total {
+                    p.set_total(total.max(count) as u64);
+                }
+                p.inc((count - reported) as u64);
+                reported = count;
             }),
         )
     } else {
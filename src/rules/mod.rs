@@ -0,0 +1,81 @@
+//! Implied-label rules applied on top of the primary tag operation.
+//!
+//! A rules file is TOML:
+//!
+//! ```toml
+//! [on_add.published]
+//! remove = ["draft", "wip"]
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TagRules {
+    #[serde(default)]
+    pub on_add: HashMap<String, AddRule>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AddRule {
+    /// Tags to remove from a page when the rule's tag is added
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+impl TagRules {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("Failed to read rules file: {}", path))?;
+        Self::parse(&content).context(format!("Failed to parse rules file: {}", path))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Tags implied for removal by adding `added`, in rule order and without
+    /// duplicates. Tags that are themselves being added are never removed.
+    pub fn removals_for_add(&self, added: &[String]) -> Vec<String> {
+        let mut removals: Vec<String> = Vec::new();
+        for tag in added {
+            if let Some(rule) = self.on_add.get(tag) {
+                for remove in &rule.remove {
+                    if !added.contains(remove) && !removals.contains(remove) {
+                        removals.push(remove.clone());
+                    }
+                }
+            }
+        }
+        removals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removals_for_add_dedups_and_skips_added_tags() {
+        let rules = TagRules::parse(
+            r#"
+            [on_add.published]
+            remove = ["draft", "wip"]
+
+            [on_add.final]
+            remove = ["wip", "review"]
+            "#,
+        )
+        .unwrap();
+
+        let added = vec![
+            "published".to_string(),
+            "final".to_string(),
+            "review".to_string(),
+        ];
+        assert_eq!(rules.removals_for_add(&added), vec!["draft", "wip"]);
+        assert!(rules.removals_for_add(&["other".to_string()]).is_empty());
+    }
+}